@@ -66,5 +66,14 @@ fn main() {
         f_sbox.write_all(&val.to_le_bytes()).unwrap();
     }
 
+    // intel_tex_2 (via image_dds) ships a C++ ASTC encoder but does not link the C++ runtime.
+    // Without this the library is left with undefined `__gxx_personality_v0` and `operator new`
+    // and no dependency on libstdc++, so loading it fails on GNU targets unless the host process
+    // happens to load libstdc++ itself. The test harness fails to link for the same reason.
+    let target = env::var("TARGET").unwrap();
+    if target.contains("linux-gnu") {
+        println!("cargo:rustc-link-lib=dylib=stdc++");
+    }
+
     println!("cargo:rerun-if-changed=build.rs");
 }
\ No newline at end of file
@@ -5,23 +5,43 @@ pub mod models;
 pub mod util;
 pub mod processing;
 
-use std::fs::File;
-use std::io::Cursor;
-use std::path::{Path, PathBuf};
+use std::path::Path;
 use std::sync::atomic::{AtomicUsize, Ordering};
-use image_dds::ddsfile::Dds;
-use image_dds::image::{DynamicImage, ImageFormat};
-use image_dds::image_from_dds;
+use std::time::Duration;
 use luadec::LuaDecompiler;
-use memmap2::Mmap;
 use mimalloc::MiMalloc;
 use rayon::prelude::IntoParallelRefIterator;
 use safer_ffi::{ffi_export};
-use safer_ffi::prelude::{char_p, repr_c};
+use safer_ffi::prelude::{c_slice, char_p, repr_c};
 use crate::models::bdo_index::BdoIndex;
+use crate::models::catalog_format::CatalogFormat;
+use crate::models::decoded_texture::DecodedTexture;
+use crate::models::entry_classification::EntryClassification;
+use crate::models::entry_details::EntryDetails;
+use crate::models::entry_layout::EntryLayout;
+use crate::models::ice_context::IceContext;
+use crate::models::loc_export_format::LocExportFormat;
+use crate::models::lua_bulk_summary::LuaBulkSummary;
+use crate::models::lua_bytecode_info::LuaBytecodeInfo;
+use crate::models::paz_archive::PazArchive;
 use crate::models::paz_file::PazFile;
-use crate::processing::bdo_decomp::BdoDecomp;
-use crate::processing::raw_ice::RawIce;
+use crate::models::texture_encode_options::{EncodeQuality, TextureEncodeOptions};
+use crate::models::texture_export_options::TextureExportOptions;
+use crate::models::thumbnail::Thumbnail;
+use crate::processing::dds_format::DdsFormat;
+use crate::processing::dds_repair::DdsRepair;
+use crate::processing::entry_detector::EntryDetector;
+use crate::processing::file_sniffer::FileSniffer;
+use crate::processing::loc_table::LocTable;
+use crate::processing::lua_bulk::{LuaBulkDecompiler, REPORT_FILE_NAME};
+use crate::processing::lua_chunk::LuaChunk;
+use crate::processing::lua_disassembler::LuaDisassembler;
+use crate::processing::paz_entry::PazEntry;
+use crate::processing::texture_catalog::TextureCatalog;
+use crate::processing::texture_encode::TextureEncode;
+use crate::processing::texture_export::{TextureError, TextureExport};
+use crate::processing::texture_preview::TexturePreview;
+use crate::processing::thumbnails::ThumbnailGenerator;
 
 #[global_allocator]
 static GLOBAL: MiMalloc = MiMalloc;
@@ -32,7 +52,7 @@ pub enum ExtractType {
     Converted = 1,
 }
 
-const BDO_ICE_KEY: [u8; 8] = [0x51, 0xF3, 0x0F, 0x11, 0x04, 0x24, 0x6A, 0x00];
+pub(crate) const BDO_ICE_KEY: [u8; 8] = [0x51, 0xF3, 0x0F, 0x11, 0x04, 0x24, 0x6A, 0x00];
 
 #[ffi_export]
 pub fn load_bdo_index(
@@ -60,65 +80,460 @@ pub fn get_file_content(
     paz_folder_path: char_p::Ref<'_>,
     file_info: PazFile,
 ) -> Option<repr_c::Vec<u8>> {
-    let folder = paz_folder_path.to_str();
+    match PazEntry::read(paz_folder_path.to_str(), &file_info) {
+        Ok(data) => Some(repr_c::Vec::from(data)),
+        Err(e) => {
+            eprintln!("Failed to read entry: {}", e);
+            None
+        }
+    }
+}
 
-    let paz_name = format!("pad{:05}.paz", file_info.paz_number);
-    let full_path = PathBuf::from(folder).join(paz_name);
+#[ffi_export]
+pub fn classify_entry(
+    paz_folder_path: char_p::Ref<'_>,
+    file_info: PazFile,
+) -> EntryClassification {
+    let Some(mmap) = PazEntry::map_paz(paz_folder_path.to_str(), file_info.paz_number) else {
+        return EntryClassification::UNREADABLE;
+    };
 
-    let file = File::open(full_path).ok()?;
-    let mmap = unsafe { Mmap::map(&file).ok()? };
+    match PazEntry::stored_bytes(&mmap, &file_info) {
+        Some(stored) => FileSniffer::classify(stored, &file_info),
+        None => EntryClassification::UNREADABLE,
+    }
+}
 
-    let start = file_info.offset as usize;
-    let end = start + file_info.compressed_size as usize;
-    if end > mmap.len() { return None; }
+#[ffi_export]
+pub fn detect_entry_layout(
+    paz_folder_path: char_p::Ref<'_>,
+    file_info: PazFile,
+) -> EntryLayout {
+    let Some(mmap) = PazEntry::map_paz(paz_folder_path.to_str(), file_info.paz_number) else {
+        return EntryLayout::UNREADABLE;
+    };
 
-    let mut data = mmap[start..end].to_vec();
+    match PazEntry::stored_bytes(&mmap, &file_info) {
+        Some(stored) => EntryDetector::detect(stored, &file_info),
+        None => EntryLayout::UNREADABLE,
+    }
+}
 
-    let mut needs_decryption = true;
+#[ffi_export]
+pub fn get_entry_details(
+    paz_folder_path: char_p::Ref<'_>,
+    file_info: PazFile,
+) -> EntryDetails {
+    let Some(mmap) = PazEntry::map_paz(paz_folder_path.to_str(), file_info.paz_number) else {
+        return EntryDetails::default();
+    };
 
-    if data.len() % 8 != 0 {
-        needs_decryption = false;
+    match PazEntry::stored_bytes(&mmap, &file_info) {
+        Some(stored) => EntryDetails::inspect(stored, &file_info),
+        None => EntryDetails::default(),
     }
-    else if data.len() >= 4 {
-        if &data[0..4] == b"PABR" {
-            needs_decryption = false;
+}
+
+#[ffi_export]
+pub fn open_paz_archive(
+    paz_folder_path: char_p::Ref<'_>,
+) -> repr_c::Box<PazArchive> {
+    Box::new(PazArchive::open(paz_folder_path.to_str())).into()
+}
+
+#[ffi_export]
+pub fn free_paz_archive(
+    _archive: repr_c::Box<PazArchive>,
+) {
+    drop(_archive);
+}
+
+#[ffi_export]
+pub fn read_entry_range(
+    archive: &PazArchive,
+    file_info: PazFile,
+    offset: u64,
+    len: u32,
+) -> Option<repr_c::Vec<u8>> {
+    match archive.read_range(&file_info, offset, len as usize) {
+        Ok(data) => Some(repr_c::Vec::from(data)),
+        Err(e) => {
+            eprintln!("Failed to read entry range: {}", e);
+            None
         }
     }
+}
 
-    if needs_decryption {
-        let ice = RawIce::new(0, &BDO_ICE_KEY);
-        if data.len() > 8192 {
-            ice.decrypt_par(&mut data);
-        } else {
-            ice.decrypt(&mut data);
+/// Decodes an entry into a caller-owned buffer and returns the decoded size.
+/// A result larger than the buffer means nothing was written; -1 means the entry is unreadable.
+#[ffi_export]
+pub fn read_file_into(
+    archive: &PazArchive,
+    file_info: PazFile,
+    buffer: c_slice::Mut<'_, u8>,
+) -> i64 {
+    match archive.read_into(&file_info, buffer.as_slice()) {
+        Ok(size) => size as i64,
+        Err(e) => {
+            eprintln!("Failed to read entry: {}", e);
+            -1
+        }
+    }
+}
+
+/// Borrowed view of a plain stored entry, valid until the archive is freed.
+/// Returns null for entries that have to be decrypted or decompressed.
+#[ffi_export]
+pub fn get_entry_view<'archive>(
+    archive: &'archive PazArchive,
+    file_info: PazFile,
+) -> Option<c_slice::Ref<'archive, u8>> {
+    archive.plain_view(&file_info).map(c_slice::Ref::from)
+}
+
+/// Returns null if the key length does not match the level (8 bytes for 0 and 1, 8 * level above).
+#[ffi_export]
+pub fn create_ice_context(
+    key: c_slice::Ref<'_, u8>,
+    level: u32,
+) -> Option<repr_c::Box<IceContext>> {
+    let context = IceContext::new(level as usize, key.as_slice())?;
+    Some(Box::new(context).into())
+}
+
+#[ffi_export]
+pub fn free_ice_context(
+    _context: repr_c::Box<IceContext>,
+) {
+    drop(_context);
+}
+
+#[ffi_export]
+pub fn ice_encrypt(
+    context: &IceContext,
+    buffer: c_slice::Mut<'_, u8>,
+    parallel: bool,
+) {
+    context.crypt(buffer.as_slice(), true, parallel);
+}
+
+#[ffi_export]
+pub fn ice_decrypt(
+    context: &IceContext,
+    buffer: c_slice::Mut<'_, u8>,
+    parallel: bool,
+) {
+    context.crypt(buffer.as_slice(), false, parallel);
+}
+
+/// Decodes a texture entry and writes it next to `output_path`, which names the file without
+/// extension; multi-image exports append a suffix per layer, face or mip.
+/// Returns the number of files written, or -1 if the entry could not be read or decoded.
+#[ffi_export]
+pub fn export_texture(
+    archive: &PazArchive,
+    file_info: PazFile,
+    options: TextureExportOptions,
+    output_path: char_p::Ref<'_>,
+) -> i32 {
+    let data = match archive.read(&file_info) {
+        Ok(data) => data,
+        Err(e) => {
+            eprintln!("Failed to export texture: {}", e);
+            return -1;
+        }
+    };
+
+    let images = match TextureExport::export(&data, &options) {
+        Ok(images) => images,
+        Err(e) => {
+            eprintln!("Failed to export texture: {}", e);
+            return -1;
+        }
+    };
+
+    let base = output_path.to_str();
+    let mut written = 0;
+    for image in images {
+        let path = format!("{}{}.{}", base, image.suffix, options.format.extension());
+        if let Some(parent) = Path::new(&path).parent() {
+            let _ = std::fs::create_dir_all(parent);
+        }
+        if std::fs::write(&path, &image.data).is_ok() {
+            written += 1;
+        }
+    }
+    written
+}
+
+/// Decodes entry `file_index` of `index` to RGBA8 for previews, starting at `mip` and using the
+/// first smaller mip that fits in `max_dim` (0 for no limit). Returns null on failure.
+#[ffi_export]
+pub fn decode_texture(
+    archive: &PazArchive,
+    index: &BdoIndex,
+    file_index: u32,
+    mip: u32,
+    max_dim: u32,
+) -> Option<repr_c::Box<DecodedTexture>> {
+    let file_info = *index.paz_files.get(file_index as usize)?;
+    let data = match archive.read(&file_info) {
+        Ok(data) => data,
+        Err(e) => {
+            eprintln!("Failed to decode texture {}: {}", file_index, e);
+            return None;
+        }
+    };
+
+    let dds = match DdsRepair::read(&data) {
+        Ok((dds, _)) => dds,
+        Err(e) => {
+            eprintln!("Failed to decode texture {}: {}", file_index, e);
+            return None;
+        }
+    };
+    let (image, decoded_mip) = match TexturePreview::decode(&dds, mip, max_dim) {
+        Ok(decoded) => decoded,
+        Err(e) => {
+            eprintln!("Failed to decode texture {}: {}", file_index, e);
+            return None;
+        }
+    };
+
+    let texture = DecodedTexture {
+        width: image.width(),
+        height: image.height(),
+        pixels: repr_c::Vec::from(image.into_raw()),
+        dxgi_format: DdsFormat::dxgi(&dds).map_or(0, |format| format as u32),
+        fourcc: DdsFormat::fourcc(&dds),
+        mip_count: dds.get_num_mipmap_levels(),
+        decoded_mip,
+    };
+    Some(Box::new(texture).into())
+}
+
+#[ffi_export]
+pub fn free_decoded_texture(
+    _texture: repr_c::Box<DecodedTexture>,
+) {
+    drop(_texture);
+}
+
+/// Encodes a PNG or TGA file into a block-compressed DDS file with a generated mip chain.
+#[ffi_export]
+pub fn encode_texture(
+    image_path: char_p::Ref<'_>,
+    output_path: char_p::Ref<'_>,
+    options: TextureEncodeOptions,
+) -> bool {
+    write_encoded_texture(image_path.to_str(), output_path.to_str(), |image| {
+        TextureEncode::encode(image, &options)
+    })
+}
+
+/// Like `encode_texture`, but uses the pixel format and mip count of the entry being replaced.
+#[ffi_export]
+pub fn encode_texture_like_entry(
+    archive: &PazArchive,
+    file_info: PazFile,
+    image_path: char_p::Ref<'_>,
+    output_path: char_p::Ref<'_>,
+    quality: EncodeQuality,
+) -> bool {
+    let original = match archive.read(&file_info) {
+        Ok(original) => original,
+        Err(e) => {
+            eprintln!("Failed to encode texture: {}", e);
+            return false;
+        }
+    };
+    write_encoded_texture(image_path.to_str(), output_path.to_str(), |image| {
+        TextureEncode::encode_like(image, &original, quality)
+    })
+}
+
+fn write_encoded_texture(
+    image_path: &str,
+    output_path: &str,
+    encode: impl FnOnce(&image_dds::image::RgbaImage) -> Result<Vec<u8>, TextureError>,
+) -> bool {
+    let extension = Path::new(image_path).extension().and_then(|e| e.to_str()).unwrap_or("");
+    let result = std::fs::read(image_path)
+        .map_err(|e| e.to_string())
+        .and_then(|data| {
+            let image = TextureEncode::load_image(&data, extension).map_err(|e| e.to_string())?;
+            encode(&image).map_err(|e| e.to_string())
+        })
+        .and_then(|dds| std::fs::write(output_path, dds).map_err(|e| e.to_string()));
+
+    match result {
+        Ok(()) => true,
+        Err(e) => {
+            eprintln!("Failed to encode texture {}: {}", image_path, e);
+            false
         }
     }
+}
+
+/// Decodes thumbnails no larger than `max_dim` for the given entries in parallel, in the order
+/// of `file_indices`. With a `cache_dir`, thumbnails are read from and written to it.
+#[ffi_export]
+pub fn generate_thumbnails(
+    archive: &PazArchive,
+    index: &BdoIndex,
+    file_indices: c_slice::Ref<'_, u32>,
+    max_dim: u32,
+    cache_dir: Option<char_p::Ref<'_>>,
+) -> repr_c::Vec<Thumbnail> {
+    let cache_dir = cache_dir.map(|dir| Path::new(dir.to_str()));
+    let generator = ThumbnailGenerator::new(archive, cache_dir, max_dim.max(1));
+
+    let file_indices = file_indices.as_slice();
+    let thumbnails: Vec<Thumbnail> = generator
+        .generate_all(index, file_indices)
+        .into_iter()
+        .zip(file_indices)
+        .map(|(image, &file_index)| match image {
+            Some(image) => Thumbnail {
+                file_index,
+                width: image.width(),
+                height: image.height(),
+                pixels: repr_c::Vec::from(image.into_raw()),
+            },
+            None => Thumbnail::missing(file_index),
+        })
+        .collect();
+    repr_c::Vec::from(thumbnails)
+}
+
+#[ffi_export]
+pub fn free_thumbnails(
+    _thumbnails: repr_c::Vec<Thumbnail>,
+) {
+    drop(_thumbnails);
+}
 
-    let is_compressed_container = if data.len() > 9 && (data[0] == 0x6E || data[0] == 0x6F) {
-        let header_original_size = u32::from_le_bytes([data[5], data[6], data[7], data[8]]);
-        header_original_size == file_info.original_size
-    } else {
-        false
+/// Writes width, height, mips, format, array size and decoded size of every `.dds`/`.dds1`
+/// entry to `output_path`. Returns the number of entries, or -1 if the file cannot be written.
+#[ffi_export]
+pub fn export_texture_catalog(
+    archive: &PazArchive,
+    index: &BdoIndex,
+    output_path: char_p::Ref<'_>,
+    format: CatalogFormat,
+) -> i64 {
+    let catalog = TextureCatalog::build(archive, index);
+    let content = match format {
+        CatalogFormat::Json => catalog.to_json(),
+        CatalogFormat::Csv => catalog.to_csv(),
     };
 
-    let final_data = if is_compressed_container {
-        let mut decompressed_buffer = vec![0u8; file_info.original_size as usize];
-        match BdoDecomp::decompress(&data, &mut decompressed_buffer) {
-            Ok(actual_size) => {
-                decompressed_buffer.truncate(actual_size as usize);
-                decompressed_buffer
+    match std::fs::write(output_path.to_str(), content) {
+        Ok(()) => catalog.entries.len() as i64,
+        Err(e) => {
+            eprintln!("Failed to write texture catalog: {}", e);
+            -1
+        }
+    }
+}
+
+/// Reads the bytecode header of each entry in `file_indices`: Lua version, format, endianness,
+/// type sizes, and whether the chunk was stripped of debug info. Runs in parallel.
+#[ffi_export]
+pub fn inspect_lua_bytecode(
+    archive: &PazArchive,
+    index: &BdoIndex,
+    file_indices: c_slice::Ref<'_, u32>,
+) -> repr_c::Vec<LuaBytecodeInfo> {
+    let infos: Vec<LuaBytecodeInfo> = file_indices
+        .as_slice()
+        .par_iter()
+        .map(|&file_index| {
+            let data = index
+                .paz_files
+                .get(file_index as usize)
+                .and_then(|file_info| archive.read(file_info).ok());
+            match data {
+                Some(data) => LuaBytecodeInfo::inspect(file_index, &data),
+                None => LuaBytecodeInfo::unreadable(file_index),
             }
-            Err(_) => return None
+        })
+        .collect();
+    repr_c::Vec::from(infos)
+}
+
+#[ffi_export]
+pub fn free_lua_bytecode_info(
+    _infos: repr_c::Vec<LuaBytecodeInfo>,
+) {
+    drop(_infos);
+}
+
+/// Decompiles every `.luac` entry into `output_folder` in parallel, giving each file at most
+/// `timeout_ms` (0 for 30 seconds). Failed entries are kept as `.luac` with a `.luac.dis`
+/// disassembly; the causes are listed in `lua_decompile_report.txt` in `output_folder`.
+///
+/// Timed-out decompiler threads cannot be stopped and keep running in the background. Once
+/// `MAX_ABANDONED_WORKERS` (8) of them are stuck, the remaining files are reported as timeouts
+/// without being attempted.
+#[ffi_export]
+pub fn decompile_lua_bulk(
+    archive: &PazArchive,
+    index: &BdoIndex,
+    output_folder: char_p::Ref<'_>,
+    timeout_ms: u32,
+    progress_callback: extern "C" fn(i32, i32),
+) -> LuaBulkSummary {
+    let timeout = Duration::from_millis(if timeout_ms == 0 { 30_000 } else { timeout_ms as u64 });
+    let output_folder = Path::new(output_folder.to_str());
+
+    let report = LuaBulkDecompiler::new(archive, timeout).run(index, output_folder, |current, total| {
+        progress_callback(current as i32, total as i32)
+    });
+
+    if let Err(e) = std::fs::create_dir_all(output_folder)
+        .and_then(|()| std::fs::write(output_folder.join(REPORT_FILE_NAME), report.to_text()))
+    {
+        eprintln!("Failed to write Lua decompile report: {}", e);
+    }
+    report.summary()
+}
+
+/// Decodes a `languagedata_*.loc` entry and writes its strings to `output_path`. Returns the
+/// number of strings, or -1 if the entry cannot be read or decoded or the file cannot be written.
+#[ffi_export]
+pub fn export_loc_table(
+    paz_folder_path: char_p::Ref<'_>,
+    file_info: PazFile,
+    output_path: char_p::Ref<'_>,
+    format: LocExportFormat,
+) -> i64 {
+    let data = match PazEntry::read(paz_folder_path.to_str(), &file_info) {
+        Ok(data) => data,
+        Err(e) => {
+            eprintln!("Failed to export localization table: {}", e);
+            return -1;
         }
-    } else {
-        let limit = file_info.original_size as usize;
-        if data.len() > limit {
-            data.truncate(limit);
+    };
+    let table = match LocTable::decode(&data) {
+        Ok(table) => table,
+        Err(e) => {
+            eprintln!("Failed to export localization table: {}", e);
+            return -1;
         }
-        data
     };
 
-    Some(repr_c::Vec::from(final_data))
+    let content = match format {
+        LocExportFormat::Tsv => table.to_tsv(),
+        LocExportFormat::Json => table.to_json(),
+    };
+    match std::fs::write(output_path.to_str(), content) {
+        Ok(()) => table.records.len() as i64,
+        Err(e) => {
+            eprintln!("Failed to write localization table: {}", e);
+            -1
+        }
+    }
 }
 
 #[ffi_export]
@@ -131,24 +546,49 @@ pub fn decompile_lua(
     paz_folder_path: char_p::Ref<'_>,
     file_info: PazFile,
 ) -> Option<repr_c::Vec<u8>> {
-    match get_file_content(paz_folder_path, file_info) {
-        Some(raw_data) => {
+    match PazEntry::read(paz_folder_path.to_str(), &file_info) {
+        Ok(raw_data) => {
+            if let Err(e) = LuaChunk::parse_header(&raw_data) {
+                let err_msg = format!("-- Unsupported Bytecode: {}", e);
+                return Some(repr_c::Vec::from(err_msg.into_bytes()));
+            }
             let decompiler = LuaDecompiler::new();
             match decompiler.decompile(&raw_data) {
                 Ok(code) => Some(repr_c::Vec::from(code.into_bytes())),
                 Err(e) => {
-                    let err_msg = format!("-- Decompile Error: {:?}", e);
-                    Some(repr_c::Vec::from(err_msg.into_bytes()))
+                    let listing = LuaDisassembler::fallback_listing(&e.to_string(), &raw_data);
+                    Some(repr_c::Vec::from(listing.into_bytes()))
                 }
             }
         }
-        None => {
+        Err(e) => {
+            eprintln!("Failed to read Lua entry: {}", e);
             let err_msg = "-- IO Error: Could not read or decrypt PAZ file".to_string();
             Some(repr_c::Vec::from(err_msg.into_bytes()))
         }
     }
 }
 
+/// Lists the bytecode of a `.luac` entry instead of decompiling it.
+#[ffi_export]
+pub fn disassemble_lua(
+    paz_folder_path: char_p::Ref<'_>,
+    file_info: PazFile,
+) -> Option<repr_c::Vec<u8>> {
+    let raw_data = match PazEntry::read(paz_folder_path.to_str(), &file_info) {
+        Ok(raw_data) => raw_data,
+        Err(e) => {
+            eprintln!("Failed to read Lua entry: {}", e);
+            return None;
+        }
+    };
+    let listing = match LuaDisassembler::disassemble_bytes(&raw_data) {
+        Ok(listing) => listing,
+        Err(e) => format!("-- Disassembly Error: {}", e),
+    };
+    Some(repr_c::Vec::from(listing.into_bytes()))
+}
+
 #[ffi_export]
 pub fn extract_files_batch(
     save_folder: char_p::Ref<'_>,
@@ -175,39 +615,59 @@ pub fn extract_files_batch(
             .join(file_name.trim_start_matches('/'));
         let mut full_output_path = base_output.join(relative_path);
 
-        let success = if let Some(data) = get_file_content(paz_folder_path, file_info) {
+        let data = PazEntry::read(paz_folder_path.to_str(), &file_info)
+            .inspect_err(|e| eprintln!("Failed to extract {}: {}", file_name, e))
+            .ok();
+        let success = if let Some(data) = data {
             let mut final_data = data;
+            let mut disassembly = None;
 
             if extract_type == 1 {
-                println!("Parse");
                 let ext = file_name.to_lowercase();
 
                 if ext.ends_with(".dds") || ext.ends_with(".dds1") {
-                    match convert_dds_to_png_memory(&final_data) {
-                        Ok(png_data) => {
-                            full_output_path.set_extension("png");
-                            final_data = png_data.into();
+                    let options = TextureExportOptions::default();
+                    match TextureExport::export(&final_data, &options) {
+                        Ok(mut images) => {
+                            full_output_path.set_extension(options.format.extension());
+                            final_data = images.swap_remove(0).data;
                         }
                         Err(e) => {
-                            println!("-- Decode Error: {:?}", e);
+                            eprintln!("-- Decode Error: {}: {}", file_name, e);
                         }
                     }
                 }
 
-                if ext.ends_with(".luac") {
-                    let decompiler = LuaDecompiler::new();
-                    // Wir dekompilieren und überschreiben final_data mit dem Ergebnis
-                    let result_code = match decompiler.decompile(&*final_data) {
-                        Ok(code) => {
-                            full_output_path.set_extension("lua");
-                            code.into_bytes()
-                        },
+                if ext.ends_with(".loc") {
+                    match LocTable::decode(&final_data) {
+                        Ok(table) => {
+                            full_output_path.set_extension("tsv");
+                            final_data = table.to_tsv().into_bytes();
+                        }
                         Err(e) => {
-                            format!("-- Decompile Error: {:?}", e).into_bytes()
+                            eprintln!("-- Decode Error: {}: {}", file_name, e);
                         }
-                    };
+                    }
+                }
 
-                    final_data = result_code.into();
+                if ext.ends_with(".luac") {
+                    if let Err(e) = LuaChunk::parse_header(&final_data) {
+                        // Not Lua 5.1: keep the original bytecode.
+                        eprintln!("-- Unsupported Bytecode: {}: {}", file_name, e);
+                    } else {
+                        let decompiler = LuaDecompiler::new();
+                        // Wir dekompilieren und überschreiben final_data mit dem Ergebnis
+                        match decompiler.decompile(&final_data) {
+                            Ok(code) => {
+                                full_output_path.set_extension("lua");
+                                final_data = code.into_bytes();
+                            }
+                            Err(e) => {
+                                eprintln!("-- Decompile Error: {}: {}", file_name, e);
+                                disassembly = Some(LuaDisassembler::fallback_listing(&e.to_string(), &final_data));
+                            }
+                        }
+                    }
                 }
             }
 
@@ -215,6 +675,10 @@ pub fn extract_files_batch(
                 let _ = std::fs::create_dir_all(parent);
             }
 
+            // Bytecode that failed to decompile is kept, with its listing next to it.
+            if let Some(listing) = disassembly {
+                let _ = std::fs::write(LuaBulkDecompiler::disassembly_path(&full_output_path), listing);
+            }
             std::fs::write(&full_output_path, &final_data[..]).is_ok()
         } else {
             false
@@ -230,18 +694,3 @@ pub fn extract_files_batch(
 
     count
 }
-
-fn convert_dds_to_png_memory(dds_buffer: &[u8]) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
-    let mut reader = Cursor::new(dds_buffer);
-    let dds = Dds::read(&mut reader)?;
-
-    let img = image_from_dds(&dds, 0)?;
-
-    let mut png_buffer = Vec::new();
-    let mut cursor = Cursor::new(&mut png_buffer);
-
-    let dynamic_img = DynamicImage::ImageRgba8(img);
-    dynamic_img.write_to(&mut cursor, ImageFormat::Png)?;
-
-    Ok(png_buffer)
-}
\ No newline at end of file
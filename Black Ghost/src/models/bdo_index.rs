@@ -15,6 +15,17 @@ pub struct BdoIndex {
 }
 
 impl BdoIndex {
+    /// Archive path of an entry, e.g. `ui_texture/icon/new_icon.dds`.
+    pub fn entry_path(&self, file_info: &PazFile) -> String {
+        let folder = self.metadata.folder_paths[file_info.folder_id as usize].folder_name.trim_matches('/');
+        let file = self.metadata.file_names[file_info.file_id as usize].trim_start_matches('/');
+        if folder.is_empty() {
+            file.to_owned()
+        } else {
+            format!("{folder}/{file}")
+        }
+    }
+
     pub fn load(path: &str, key: &[u8]) -> Result<Self, Box<dyn std::error::Error>> {
         let file = File::open(path)?;
         let data = unsafe { Mmap::map(&file)? };
@@ -0,0 +1,19 @@
+use safer_ffi::derive_ReprC;
+use safer_ffi::prelude::repr_c;
+
+/// RGBA8 preview of a texture entry, together with the format it was stored in.
+#[derive_ReprC]
+#[repr(C)]
+pub struct DecodedTexture {
+    /// Tightly packed rows of `width * 4` bytes.
+    pub pixels: repr_c::Vec<u8>,
+    pub width: u32,
+    pub height: u32,
+    /// `DXGI_FORMAT` value of the stored data, 0 (unknown) if it has no DXGI equivalent.
+    pub dxgi_format: u32,
+    /// FourCC of legacy headers, 0 if the header has none.
+    pub fourcc: u32,
+    pub mip_count: u32,
+    /// Mip level the pixels were decoded from.
+    pub decoded_mip: u32,
+}
@@ -0,0 +1,53 @@
+use safer_ffi::derive_ReprC;
+
+#[derive_ReprC]
+#[repr(i32)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FileKind {
+    Unknown = 0,
+    Dds = 1,
+    Png = 2,
+    Jpeg = 3,
+    Bmp = 4,
+    LuaBytecode = 5,
+    LuaJitBytecode = 6,
+    Xml = 7,
+    Utf16Text = 8,
+    Utf8Text = 9,
+    WebM = 10,
+    WwiseBank = 11,
+    WwiseWem = 12,
+    Ogg = 13,
+    Pabr = 14,
+    TrueType = 15,
+    OpenType = 16,
+}
+
+#[derive_ReprC]
+#[repr(i32)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum SniffConfidence {
+    None = 0,
+    Low = 1,
+    Medium = 2,
+    High = 3,
+}
+
+#[derive_ReprC]
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct EntryClassification {
+    pub kind: FileKind,
+    pub confidence: SniffConfidence,
+    pub is_encrypted: bool,
+    pub is_compressed: bool,
+}
+
+impl EntryClassification {
+    pub const UNREADABLE: Self = Self {
+        kind: FileKind::Unknown,
+        confidence: SniffConfidence::None,
+        is_encrypted: false,
+        is_compressed: false,
+    };
+}
@@ -0,0 +1,96 @@
+use safer_ffi::derive_ReprC;
+use crate::models::entry_layout::DetectionStatus;
+use crate::models::paz_file::PazFile;
+use crate::processing::entry_detector::EntryDetector;
+
+#[derive_ReprC]
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct EntryDetails {
+    pub is_readable: bool,
+    pub detection_status: DetectionStatus,
+    pub is_encrypted: bool,
+    pub has_container: bool,
+    pub container_flags: u8,
+    /// Flag bit 0x01: the payload is compressed rather than stored.
+    pub is_compressed: bool,
+    /// Flag bit 0x02: 9-byte header with 32-bit lengths instead of the 3-byte form.
+    pub has_long_header: bool,
+    pub header_compressed_size: u32,
+    pub header_original_size: u32,
+    pub compression_ratio: f32,
+    pub compressed_size_matches: bool,
+    pub original_size_matches: bool,
+}
+
+impl EntryDetails {
+    pub fn inspect(stored: &[u8], file_info: &PazFile) -> Self {
+        let layout = EntryDetector::detect(stored, file_info);
+        let mut details = EntryDetails {
+            is_readable: true,
+            detection_status: layout.status,
+            is_encrypted: layout.is_encrypted,
+            ..Default::default()
+        };
+
+        if !layout.has_container {
+            // Without a container the stored bytes are the file itself, plus block padding.
+            let original = file_info.original_size as usize;
+            let sizes_agree = stored.len() >= original && stored.len() - original < 8;
+            details.compression_ratio = 1.0;
+            details.compressed_size_matches = sizes_agree;
+            details.original_size_matches = sizes_agree;
+            return details;
+        }
+
+        let (comp_len, decomp_len) = (layout.header_compressed_size, layout.header_original_size);
+        details.has_container = true;
+        details.container_flags = layout.container_flags;
+        details.is_compressed = layout.is_compressed();
+        details.has_long_header = layout.has_long_header();
+        details.header_compressed_size = comp_len;
+        details.header_original_size = decomp_len;
+        details.compression_ratio = if decomp_len == 0 {
+            0.0
+        } else {
+            comp_len as f32 / decomp_len as f32
+        };
+
+        let stored_len = file_info.compressed_size;
+        details.compressed_size_matches = comp_len <= stored_len && stored_len - comp_len < 8;
+        details.original_size_matches = decomp_len == file_info.original_size;
+
+        details
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::processing::test_support::{compressed_container, encrypt_entry, padded, paz_file, Lcg};
+
+    #[test]
+    fn reports_plain_text_starting_like_a_flag_byte_without_container() {
+        let stored = b"local x = 1";
+        let details = EntryDetails::inspect(stored, &paz_file(stored, stored.len()));
+
+        assert!(!details.has_container && !details.is_compressed && !details.has_long_header);
+        assert_eq!((details.container_flags, details.header_original_size), (0, 0));
+        assert_eq!(details.compression_ratio, 1.0);
+        assert!(details.compressed_size_matches && details.original_size_matches);
+    }
+
+    #[test]
+    fn reports_container_sizes_from_the_header() {
+        let (container, content) = compressed_container(&mut Lcg(0x27), 4096);
+        let comp_len = container.len() as u32;
+        let stored = encrypt_entry(&padded(container));
+        let details = EntryDetails::inspect(&stored, &paz_file(&stored, content.len()));
+
+        assert!(details.is_encrypted && details.has_container);
+        assert!(details.is_compressed && details.has_long_header);
+        assert_eq!(details.container_flags, 0x6F);
+        assert_eq!((details.header_compressed_size, details.header_original_size), (comp_len, content.len() as u32));
+        assert!(details.compressed_size_matches && details.original_size_matches);
+    }
+}
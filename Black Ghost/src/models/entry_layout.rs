@@ -0,0 +1,56 @@
+use safer_ffi::derive_ReprC;
+
+#[derive_ReprC]
+#[repr(i32)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DetectionStatus {
+    /// A container header or a known magic validated against the meta sizes.
+    Certain = 0,
+    /// Only weak content evidence (e.g. text) supports the decision.
+    Probable = 1,
+    /// Neither interpretation could be validated; the entry is used as stored.
+    Uncertain = 2,
+    /// The stored bytes contradict the meta sizes.
+    Inconsistent = 3,
+    #[default]
+    Unreadable = 4,
+}
+
+#[derive_ReprC]
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct EntryLayout {
+    pub status: DetectionStatus,
+    pub is_encrypted: bool,
+    pub has_container: bool,
+    pub container_flags: u8,
+    pub header_size: u8,
+    pub header_compressed_size: u32,
+    pub header_original_size: u32,
+}
+
+impl EntryLayout {
+    pub const UNREADABLE: Self = Self {
+        status: DetectionStatus::Unreadable,
+        is_encrypted: false,
+        has_container: false,
+        container_flags: 0,
+        header_size: 0,
+        header_compressed_size: 0,
+        header_original_size: 0,
+    };
+
+    pub fn plain(status: DetectionStatus, is_encrypted: bool) -> Self {
+        Self { status, is_encrypted, ..Self::UNREADABLE }
+    }
+
+    #[inline]
+    pub fn is_compressed(&self) -> bool {
+        self.has_container && (self.container_flags & 0x01) != 0
+    }
+
+    #[inline]
+    pub fn has_long_header(&self) -> bool {
+        self.has_container && (self.container_flags & 0x02) != 0
+    }
+}
@@ -0,0 +1,89 @@
+use safer_ffi::derive_ReprC;
+use crate::processing::raw_ice::RawIce;
+
+/// ICE key schedule handed to the host, so buffers can be transformed with arbitrary keys.
+#[derive_ReprC]
+#[repr(opaque)]
+pub struct IceContext {
+    ice: RawIce,
+}
+
+impl IceContext {
+    /// Returns `None` if `key` does not have the length `level` requires.
+    pub fn new(level: usize, key: &[u8]) -> Option<Self> {
+        if key.len() != RawIce::key_len(level) {
+            return None;
+        }
+        Some(Self { ice: RawIce::new(level, key) })
+    }
+
+    /// Encrypts or decrypts whole 8-byte blocks; a shorter tail is left as is, like the game does.
+    pub fn crypt(&self, data: &mut [u8], encrypt: bool, parallel: bool) {
+        let aligned = data.len() & !7;
+        let data = &mut data[..aligned];
+        match (encrypt, parallel) {
+            (true, true) => self.ice.encrypt_par(data),
+            (true, false) => self.ice.encrypt(data),
+            (false, true) => self.ice.decrypt_par(data),
+            (false, false) => self.ice.decrypt(data),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use safer_ffi::prelude::c_slice;
+    use super::*;
+    use crate::{create_ice_context, ice_decrypt, ice_encrypt, BDO_ICE_KEY};
+    use crate::processing::test_support::Lcg;
+
+    #[test]
+    fn rejects_keys_of_the_wrong_length() {
+        assert!(IceContext::new(0, &BDO_ICE_KEY[..7]).is_none());
+        assert!(IceContext::new(1, &[0; 16]).is_none());
+        assert!(IceContext::new(2, &[0; 8]).is_none());
+        assert!(IceContext::new(2, &[0; 16]).is_some());
+        assert!(create_ice_context(c_slice::Ref::from(&[0u8; 12][..]), 0).is_none());
+    }
+
+    #[test]
+    fn round_trips_and_leaves_the_tail_untouched() {
+        let mut rng = Lcg(0x39);
+        // Long enough for the parallel batches, plus a few blocks after the last batch.
+        let plain: Vec<u8> = (0..64 * 1024 + 24 + 5).map(|_| rng.next() as u8).collect();
+        let key: Vec<u8> = (0..24).map(|_| rng.next() as u8).collect();
+        let context = IceContext::new(3, &key).unwrap();
+
+        let mut serial = plain.clone();
+        context.crypt(&mut serial, true, false);
+        let mut parallel = plain.clone();
+        context.crypt(&mut parallel, true, true);
+
+        assert_eq!(serial, parallel);
+        assert_ne!(serial[..8], plain[..8]);
+        assert_eq!(serial[plain.len() - 5..], plain[plain.len() - 5..]);
+
+        context.crypt(&mut serial, false, true);
+        context.crypt(&mut parallel, false, false);
+        assert_eq!(serial, plain);
+        assert_eq!(parallel, plain);
+    }
+
+    #[test]
+    fn ffi_functions_match_the_archive_cipher() {
+        let plain = b"twenty-one byte entry".to_vec();
+        let context = create_ice_context(c_slice::Ref::from(&BDO_ICE_KEY[..]), 0).unwrap();
+
+        for parallel in [false, true] {
+            let mut buffer = plain.clone();
+            ice_encrypt(&context, c_slice::Mut::from(&mut buffer[..]), parallel);
+
+            let mut expected = plain.clone();
+            RawIce::new(0, &BDO_ICE_KEY).encrypt(&mut expected[..16]);
+            assert_eq!(buffer, expected);
+
+            ice_decrypt(&context, c_slice::Mut::from(&mut buffer[..]), parallel);
+            assert_eq!(buffer, plain);
+        }
+    }
+}
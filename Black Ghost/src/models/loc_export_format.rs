@@ -0,0 +1,9 @@
+use safer_ffi::derive_ReprC;
+
+#[derive_ReprC]
+#[repr(i32)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LocExportFormat {
+    Tsv = 0,
+    Json = 1,
+}
@@ -0,0 +1,17 @@
+use safer_ffi::derive_ReprC;
+
+/// Outcome counts of a bulk Lua decompile. Every entry is counted exactly once.
+#[derive_ReprC]
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct LuaBulkSummary {
+    pub total: u32,
+    pub decompiled: u32,
+    pub decompile_errors: u32,
+    pub timeouts: u32,
+    pub panics: u32,
+    /// Not Lua 5.1 bytecode, e.g. LuaJIT chunks.
+    pub unsupported: u32,
+    pub unreadable: u32,
+    pub write_errors: u32,
+}
@@ -0,0 +1,167 @@
+use safer_ffi::derive_ReprC;
+use crate::processing::lua_chunk::{LuaChunk, LuaChunkError, LuaHeader};
+
+#[derive_ReprC]
+#[repr(i32)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LuaBytecodeStatus {
+    /// A Lua 5.1 chunk that parses completely.
+    Supported = 0,
+    /// The entry could not be read or decrypted.
+    Unreadable = 1,
+    NotLua = 2,
+    LuaJit = 3,
+    UnsupportedVersion = 4,
+    /// Lua 5.1, but with a format or type sizes the tools cannot read.
+    UnsupportedLayout = 5,
+    /// The header is fine but the chunk behind it is truncated or malformed.
+    Corrupt = 6,
+}
+
+/// Header fields and debug-info state of a `.luac` entry.
+#[derive_ReprC]
+#[repr(C)]
+pub struct LuaBytecodeInfo {
+    pub file_index: u32,
+    pub status: LuaBytecodeStatus,
+    /// `0x51` for Lua 5.1; the dump version for LuaJIT. The remaining header fields are as
+    /// stored and only filled in for `\x1bLua` chunks.
+    pub version: u8,
+    pub format: u8,
+    pub little_endian: bool,
+    pub int_size: u8,
+    pub size_t_size: u8,
+    pub instruction_size: u8,
+    pub number_size: u8,
+    /// Numbers are stored as integers instead of floating point.
+    pub integral: bool,
+    /// No line info, local or upvalue names. Only known for `Supported` chunks.
+    pub stripped: bool,
+    /// Main function plus nested functions; 0 unless `Supported`.
+    pub function_count: u32,
+    /// Why the chunk is not supported, empty for `Supported`.
+    pub message: safer_ffi::String,
+}
+
+impl LuaBytecodeInfo {
+    pub fn inspect(file_index: u32, data: &[u8]) -> Self {
+        let mut info = Self::unreadable(file_index);
+
+        let header = match LuaHeader::read(data) {
+            Ok(header) => header,
+            Err(e) => {
+                info.status = Self::status(&e);
+                if let LuaChunkError::LuaJit { version } = e {
+                    info.version = version;
+                }
+                info.message = e.to_string().into();
+                return info;
+            }
+        };
+
+        info.version = header.version;
+        info.format = header.format;
+        info.little_endian = header.little_endian;
+        info.int_size = header.int_size;
+        info.size_t_size = header.size_t_size;
+        info.instruction_size = header.instruction_size;
+        info.number_size = header.number_size;
+        info.integral = header.integral;
+
+        match LuaChunk::parse(data) {
+            Ok(chunk) => {
+                info.status = LuaBytecodeStatus::Supported;
+                info.stripped = chunk.is_stripped();
+                info.function_count = chunk.function_count();
+                info.message = String::new().into();
+            }
+            Err(e) => {
+                info.status = Self::status(&e);
+                info.message = e.to_string().into();
+            }
+        }
+        info
+    }
+
+    pub fn unreadable(file_index: u32) -> Self {
+        Self {
+            file_index,
+            status: LuaBytecodeStatus::Unreadable,
+            version: 0,
+            format: 0,
+            little_endian: false,
+            int_size: 0,
+            size_t_size: 0,
+            instruction_size: 0,
+            number_size: 0,
+            integral: false,
+            stripped: false,
+            function_count: 0,
+            message: "entry could not be read".to_string().into(),
+        }
+    }
+
+    fn status(error: &LuaChunkError) -> LuaBytecodeStatus {
+        match error {
+            LuaChunkError::NotLuaChunk => LuaBytecodeStatus::NotLua,
+            LuaChunkError::LuaJit { .. } => LuaBytecodeStatus::LuaJit,
+            LuaChunkError::UnsupportedVersion { .. } => LuaBytecodeStatus::UnsupportedVersion,
+            LuaChunkError::UnsupportedFormat { .. } | LuaChunkError::UnsupportedSizes { .. } => {
+                LuaBytecodeStatus::UnsupportedLayout
+            }
+            LuaChunkError::Truncated { .. } | LuaChunkError::InvalidConstant { .. } | LuaChunkError::TooDeep => {
+                LuaBytecodeStatus::Corrupt
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::processing::test_support::lua_chunk;
+
+    #[test]
+    fn fills_supported_chunks_completely() {
+        let info = LuaBytecodeInfo::inspect(7, &lua_chunk(false));
+        assert_eq!((info.file_index, info.status), (7, LuaBytecodeStatus::Supported));
+        assert_eq!((info.version, info.format, info.little_endian), (0x51, 0, true));
+        assert_eq!((info.int_size, info.size_t_size, info.instruction_size, info.number_size), (4, 4, 4, 8));
+        assert_eq!((info.stripped, info.function_count), (false, 2));
+        assert!(info.message.is_empty());
+
+        assert!(LuaBytecodeInfo::inspect(7, &lua_chunk(true)).stripped);
+    }
+
+    #[test]
+    fn maps_errors_to_statuses() {
+        let info = LuaBytecodeInfo::inspect(1, b"-- plain source");
+        assert_eq!((info.status, info.version), (LuaBytecodeStatus::NotLua, 0));
+
+        let info = LuaBytecodeInfo::inspect(1, b"\x1bLJ\x02\x00");
+        assert_eq!((info.status, info.version, info.int_size), (LuaBytecodeStatus::LuaJit, 2, 0));
+
+        let mut chunk = lua_chunk(false);
+        chunk[4] = 0x52;
+        let info = LuaBytecodeInfo::inspect(1, &chunk);
+        assert_eq!((info.status, info.version), (LuaBytecodeStatus::UnsupportedVersion, 0x52));
+
+        let info = LuaBytecodeInfo::inspect(1, &lua_chunk(false)[..8]);
+        assert_eq!((info.status, info.version), (LuaBytecodeStatus::Corrupt, 0));
+    }
+
+    #[test]
+    fn fills_header_fields_of_partly_supported_chunks() {
+        let mut chunk = lua_chunk(false);
+        chunk[7] = 2;
+        let info = LuaBytecodeInfo::inspect(3, &chunk);
+        assert_eq!(info.status, LuaBytecodeStatus::UnsupportedLayout);
+        assert_eq!((info.version, info.int_size, info.size_t_size, info.number_size), (0x51, 2, 4, 8));
+        assert_eq!(info.function_count, 0);
+        assert!(!info.message.is_empty());
+
+        let chunk = lua_chunk(false);
+        let info = LuaBytecodeInfo::inspect(3, &chunk[..chunk.len() - 6]);
+        assert_eq!((info.status, info.version, info.int_size), (LuaBytecodeStatus::Corrupt, 0x51, 4));
+    }
+}
@@ -1,3 +1,16 @@
 ﻿pub mod bdo_index;
 pub mod pad_meta;
 pub mod paz_file;
+pub mod entry_classification;
+pub mod entry_details;
+pub mod entry_layout;
+pub mod paz_archive;
+pub mod ice_context;
+pub mod texture_export_options;
+pub mod decoded_texture;
+pub mod texture_encode_options;
+pub mod thumbnail;
+pub mod catalog_format;
+pub mod lua_bytecode_info;
+pub mod lua_bulk_summary;
+pub mod loc_export_format;
\ No newline at end of file
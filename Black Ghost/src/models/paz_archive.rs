@@ -0,0 +1,406 @@
+use std::borrow::Cow;
+use std::collections::{HashMap, VecDeque};
+use std::io::{Read, Seek, SeekFrom};
+use std::sync::{Arc, Mutex};
+use memmap2::Mmap;
+use safer_ffi::derive_ReprC;
+use crate::models::entry_layout::EntryLayout;
+use crate::models::paz_file::PazFile;
+use crate::processing::bdo_decomp::BdoDecomp;
+use crate::processing::entry_detector::EntryDetector;
+use crate::processing::entry_reader::{ContainerStream, EntryReader};
+use crate::processing::paz_entry::{EntryReadError, PazEntry};
+use crate::util::buffer_cache::BufferCache;
+
+const DECOMPRESSED_CACHE_BYTES: usize = 256 * 1024 * 1024;
+/// Compressed entries larger than this are never decoded as a whole for range reads.
+const WHOLE_DECODE_LIMIT: usize = 16 * 1024 * 1024;
+/// Decoder states kept for large compressed entries.
+const STREAM_STATES: usize = 8;
+
+/// Keeps the `.paz` files of a game folder mapped and caches decompressed entries
+/// for repeated partial reads.
+///
+/// Small compressed entries are decoded once and cached whole. Large ones are decoded only up
+/// to the requested range, and their decoder state is kept so the next page continues from there.
+#[derive_ReprC]
+#[repr(opaque)]
+pub struct PazArchive {
+    paz_folder_path: String,
+    paz_maps: Mutex<HashMap<u32, Arc<Mmap>>>,
+    decompressed: Mutex<BufferCache<(u32, u32)>>,
+    // Most recently used states live at the back.
+    streams: Mutex<VecDeque<((u32, u32), ContainerStream)>>,
+}
+
+impl PazArchive {
+    pub fn open(paz_folder_path: &str) -> Self {
+        Self {
+            paz_folder_path: paz_folder_path.to_owned(),
+            paz_maps: Mutex::new(HashMap::new()),
+            decompressed: Mutex::new(BufferCache::new(DECOMPRESSED_CACHE_BYTES)),
+            streams: Mutex::new(VecDeque::new()),
+        }
+    }
+
+    pub fn paz(&self, paz_number: u32) -> Option<Arc<Mmap>> {
+        let mut maps = self.paz_maps.lock().unwrap();
+        if let Some(mmap) = maps.get(&paz_number) {
+            return Some(mmap.clone());
+        }
+
+        let mmap = Arc::new(PazEntry::map_paz(&self.paz_folder_path, paz_number)?);
+        maps.insert(paz_number, mmap.clone());
+        Some(mmap)
+    }
+
+    /// Stored bytes of an entry, borrowed from the archive's mapping of its `.paz` file.
+    pub fn stored_bytes(&self, file_info: &PazFile) -> Option<&[u8]> {
+        self.entry_bytes(file_info).ok()
+    }
+
+    fn entry_bytes(&self, file_info: &PazFile) -> Result<&[u8], EntryReadError> {
+        let paz_number = file_info.paz_number;
+        let mmap = self.paz(paz_number).ok_or(EntryReadError::PazUnavailable { paz_number })?;
+        let stored = PazEntry::stored_bytes(&mmap, file_info).ok_or_else(|| EntryReadError::out_of_bounds(file_info))?;
+
+        // SAFETY: mappings are only ever added to `paz_maps`, so the `Arc` keeps this
+        // memory mapped for as long as `self` is alive.
+        Ok(unsafe { std::slice::from_raw_parts(stored.as_ptr(), stored.len()) })
+    }
+
+    /// Returns the content of an entry that is stored neither encrypted nor compressed
+    /// as a view into the mapped archive, without copying it.
+    pub fn plain_view(&self, file_info: &PazFile) -> Option<&[u8]> {
+        let stored = self.stored_bytes(file_info)?;
+        let layout = EntryDetector::detect(stored, file_info);
+        Self::plain_content(stored, file_info, &layout)
+    }
+
+    pub fn entry_reader(&self, file_info: &PazFile) -> Option<EntryReader<'_>> {
+        let stored = self.stored_bytes(file_info)?;
+        Some(EntryReader::new(stored, *file_info))
+    }
+
+    /// Decoded content of an entry; plain stored entries are borrowed instead of copied.
+    pub fn read(&self, file_info: &PazFile) -> Result<Cow<'_, [u8]>, EntryReadError> {
+        let stored = self.entry_bytes(file_info)?;
+        let layout = EntryDetector::detect(stored, file_info);
+
+        match Self::plain_content(stored, file_info, &layout) {
+            Some(content) => Ok(Cow::Borrowed(content)),
+            None => PazEntry::decode_with(stored, file_info, &layout)
+                .map(Cow::Owned)
+                .map_err(|source| EntryReadError::Decompress { hash: file_info.hash, source }),
+        }
+    }
+
+    /// Decodes an entry directly into `output` and returns its decoded size.
+    /// Nothing is written if `output` is smaller than that size.
+    pub fn read_into(&self, file_info: &PazFile, output: &mut [u8]) -> Result<usize, EntryReadError> {
+        let stored = self.entry_bytes(file_info)?;
+        let layout = EntryDetector::detect(stored, file_info);
+
+        let needed = if layout.has_container {
+            layout.header_original_size as usize
+        } else {
+            stored.len().min(file_info.original_size as usize)
+        };
+        if output.len() < needed {
+            return Ok(needed);
+        }
+        let output = &mut output[..needed];
+
+        if layout.has_container {
+            let written = if layout.is_encrypted {
+                let mut decrypted = stored.to_vec();
+                PazEntry::decrypt_in_place(&mut decrypted);
+                BdoDecomp::decompress_strict(&decrypted, output)
+            } else {
+                BdoDecomp::decompress_strict(stored, output)
+            };
+            return written
+                .map(|written| written as usize)
+                .map_err(|source| EntryReadError::Decompress { hash: file_info.hash, source });
+        }
+
+        output.copy_from_slice(&stored[..needed]);
+        if layout.is_encrypted {
+            // The last block may be cut by the original size, so it is decrypted separately.
+            let whole = needed - needed % 8;
+            PazEntry::decrypt_in_place(&mut output[..whole]);
+
+            if whole < needed && whole + 8 <= stored.len() {
+                let mut block = [0u8; 8];
+                block.copy_from_slice(&stored[whole..whole + 8]);
+                PazEntry::decrypt_in_place(&mut block);
+                output[whole..].copy_from_slice(&block[..needed - whole]);
+            }
+        }
+
+        Ok(needed)
+    }
+
+    fn plain_content<'a>(stored: &'a [u8], file_info: &PazFile, layout: &EntryLayout) -> Option<&'a [u8]> {
+        let original = file_info.original_size as usize;
+        if layout.is_encrypted || layout.has_container || stored.len() < original {
+            return None;
+        }
+
+        Some(&stored[..original])
+    }
+
+    /// Reads `len` bytes of the decoded entry starting at `offset`, clamped to the entry size.
+    pub fn read_range(&self, file_info: &PazFile, offset: u64, len: usize) -> Result<Vec<u8>, EntryReadError> {
+        let stored = self.entry_bytes(file_info)?;
+        let layout = EntryDetector::detect(stored, file_info);
+
+        let original = file_info.original_size as usize;
+        let start = (offset.min(original as u64)) as usize;
+        let end = start + len.min(original - start);
+
+        if layout.has_container && layout.header_original_size as usize > WHOLE_DECODE_LIMIT {
+            return self.read_streamed(stored, file_info, layout, start, end);
+        }
+
+        if layout.has_container {
+            let key = (file_info.paz_number, file_info.offset);
+            let cached = self.decompressed.lock().unwrap().get(&key);
+            let content = match cached {
+                Some(content) => content,
+                None => {
+                    let content = PazEntry::decode_with(stored, file_info, &layout)
+                        .map_err(|source| EntryReadError::Decompress { hash: file_info.hash, source })?;
+                    let content = Arc::new(content);
+                    self.decompressed.lock().unwrap().insert(key, content.clone());
+                    content
+                }
+            };
+
+            let end = end.min(content.len());
+            return Ok(content[start.min(end)..end].to_vec());
+        }
+
+        let end = end.min(stored.len());
+        let start = start.min(end);
+        if !layout.is_encrypted {
+            return Ok(stored[start..end].to_vec());
+        }
+
+        // Only the ICE blocks overlapping the range are copied and decrypted.
+        let block_start = start & !7;
+        let block_end = end.next_multiple_of(8).min(stored.len());
+        let mut blocks = stored[block_start..block_end].to_vec();
+        PazEntry::decrypt_in_place(&mut blocks);
+
+        Ok(blocks[start - block_start..end - block_start].to_vec())
+    }
+
+    fn read_streamed(
+        &self,
+        stored: &[u8],
+        file_info: &PazFile,
+        layout: EntryLayout,
+        start: usize,
+        end: usize,
+    ) -> Result<Vec<u8>, EntryReadError> {
+        let key = (file_info.paz_number, file_info.offset);
+        let stream = {
+            let mut streams = self.streams.lock().unwrap();
+            let pos = streams.iter().position(|(k, _)| *k == key);
+            pos.and_then(|pos| streams.remove(pos)).map(|(_, stream)| stream)
+        };
+
+        let mut reader = EntryReader::with_layout(stored, *file_info, layout);
+        if let Some(stream) = stream {
+            reader = reader.with_stream(stream);
+        }
+
+        let mut range = Vec::with_capacity(end - start);
+        let result = reader
+            .seek(SeekFrom::Start(start as u64))
+            .and_then(|_| (&mut reader).take((end - start) as u64).read_to_end(&mut range));
+
+        // A stream that failed to decode is dropped, so the next read starts over.
+        if result.is_ok()
+            && let Some(stream) = reader.into_stream()
+        {
+            let mut streams = self.streams.lock().unwrap();
+            if streams.len() >= STREAM_STATES {
+                streams.pop_front();
+            }
+            streams.push_back((key, stream));
+        }
+
+        result.map_err(|source| EntryReadError::Stream { hash: file_info.hash, source })?;
+        Ok(range)
+    }
+
+    pub fn clear_cache(&self) {
+        self.decompressed.lock().unwrap().clear();
+        self.streams.lock().unwrap().clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::processing::bdo_decomp::BdoDecompError;
+    use crate::processing::test_support::{compressed_container, encrypt_entry, padded, paz_folder, short_container, Lcg};
+
+    #[test]
+    fn borrows_only_plain_stored_entries() {
+        let text = b"-- plain script\nlocal answer = 42\nreturn answer\n".to_vec();
+        let (container, content) = compressed_container(&mut Lcg(0x31), 4096);
+        let (dir, files) = paz_folder(
+            "plain_view",
+            &[(text.clone(), text.len()), (encrypt_entry(&text), text.len()), (container, content.len())],
+        );
+        let archive = PazArchive::open(dir.to_str().unwrap());
+
+        let view = archive.plain_view(&files[0]).unwrap();
+        assert_eq!(view, &text[..]);
+        assert_eq!(view.as_ptr(), archive.stored_bytes(&files[0]).unwrap().as_ptr());
+        assert!(matches!(archive.read(&files[0]), Ok(Cow::Borrowed(_))));
+
+        assert!(archive.plain_view(&files[1]).is_none());
+        assert!(archive.plain_view(&files[2]).is_none());
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn reads_ranges_of_encrypted_unaligned_entries() {
+        let mut rng = Lcg(0x3A);
+        let mut content: Vec<u8> = (0..1003).map(|_| rng.next() as u8).collect();
+        content[..4].copy_from_slice(b"DDS ");
+        let (dir, files) = paz_folder("range_plain", &[(encrypt_entry(&content), content.len())]);
+        let archive = PazArchive::open(dir.to_str().unwrap());
+
+        for (offset, len) in [(0, 10), (5, 16), (990, 13), (996, 100), (1003, 4), (5000, 4)] {
+            let start = offset.min(content.len());
+            let end = (offset + len).min(content.len());
+            assert_eq!(
+                archive.read_range(&files[0], offset as u64, len).unwrap(),
+                &content[start..end],
+                "offset {offset}, len {len}"
+            );
+        }
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn rejects_containers_ending_short_of_their_size() {
+        let (container, expected) = short_container(&mut Lcg(0x35), 2000);
+        let (dir, files) = paz_folder("short_container", &[(padded(container), expected.len())]);
+        let archive = PazArchive::open(dir.to_str().unwrap());
+
+        assert!(archive.read(&files[0]).is_err());
+        assert!(matches!(
+            archive.read_range(&files[0], 0, 16),
+            Err(EntryReadError::Decompress { source: BdoDecompError::SizeMismatch { .. }, .. })
+        ));
+        let mut output = vec![0u8; expected.len()];
+        assert!(matches!(
+            archive.read_into(&files[0], &mut output),
+            Err(EntryReadError::Decompress { source: BdoDecompError::SizeMismatch { .. }, .. })
+        ));
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn reports_streaming_errors_of_large_entries() {
+        let (container, expected) = short_container(&mut Lcg(0x30), WHOLE_DECODE_LIMIT + 1024 * 1024);
+        let (dir, files) = paz_folder("short_stream", &[(padded(container), expected.len())]);
+        let archive = PazArchive::open(dir.to_str().unwrap());
+
+        assert_eq!(archive.read_range(&files[0], 4096, 64).unwrap(), &expected[4096..4160]);
+        match archive.read_range(&files[0], expected.len() as u64 - 16, 16) {
+            Err(EntryReadError::Stream { hash, source }) => {
+                assert_eq!(hash, files[0].hash);
+                assert_eq!(source.kind(), std::io::ErrorKind::InvalidData);
+            }
+            other => panic!("unexpected result {:?}", other),
+        }
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn reads_ranges_of_small_and_large_compressed_entries() {
+        let mut rng = Lcg(0xC0);
+        let (small, small_content) = compressed_container(&mut rng, 100_000);
+        let (large, large_content) = compressed_container(&mut rng, WHOLE_DECODE_LIMIT + 4 * 1024 * 1024);
+        let (dir, files) = paz_folder(
+            "range_compressed",
+            &[
+                (encrypt_entry(&padded(small)), small_content.len()),
+                (encrypt_entry(&padded(large)), large_content.len()),
+            ],
+        );
+        let archive = PazArchive::open(dir.to_str().unwrap());
+
+        assert_eq!(archive.read_range(&files[0], 70_001, 300).unwrap(), &small_content[70_001..70_301]);
+        assert_eq!(archive.read_range(&files[0], 17, 40).unwrap(), &small_content[17..57]);
+        assert!(archive.streams.lock().unwrap().is_empty());
+
+        // Forward, back to the start, then past the first decoder snapshot.
+        let len = large_content.len();
+        for offset in [len - 3 * 1024 * 1024, 1_000_000, len - 100, len - 5] {
+            assert_eq!(
+                archive.read_range(&files[1], offset as u64, 4096).unwrap(),
+                &large_content[offset..(offset + 4096).min(len)],
+                "offset {offset}"
+            );
+        }
+        assert_eq!(archive.streams.lock().unwrap().len(), 1);
+        assert!(archive.decompressed.lock().unwrap().get(&(1, files[1].offset)).is_none());
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn read_into_reports_size_without_writing_small_buffers() {
+        let content = b"<?xml version=\"1.0\"?><root/>\n";
+        let (container, expected) = compressed_container(&mut Lcg(0x32), 100);
+        let (dir, files) = paz_folder(
+            "read_into_small",
+            &[(encrypt_entry(content), content.len()), (encrypt_entry(&padded(container)), expected.len())],
+        );
+        let archive = PazArchive::open(dir.to_str().unwrap());
+
+        let mut output = [0xAAu8; 8];
+        assert_eq!(archive.read_into(&files[0], &mut output).unwrap(), content.len());
+        assert_eq!(archive.read_into(&files[1], &mut output).unwrap(), expected.len());
+        assert_eq!(output, [0xAA; 8]);
+
+        let mut output = vec![0u8; 256];
+        assert_eq!(archive.read_into(&files[1], &mut output).unwrap(), expected.len());
+        assert_eq!(&output[..expected.len()], &expected[..]);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn read_into_keeps_unaligned_tail_unencrypted() {
+        let content = b"<?xml version=\"1.0\"?><root/>\n";
+        let (dir, files) = paz_folder("read_into_tail", &[(encrypt_entry(content), content.len())]);
+        let archive = PazArchive::open(dir.to_str().unwrap());
+
+        let mut output = [0u8; 64];
+        assert_eq!(archive.read_into(&files[0], &mut output).unwrap(), content.len());
+        assert_eq!(&output[..content.len()], content);
+        assert!(output[content.len()..].iter().all(|&b| b == 0));
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn read_into_reports_missing_paz_files() {
+        let (dir, files) = paz_folder("read_into_missing", &[(b"plain".to_vec(), 5)]);
+        let archive = PazArchive::open(dir.to_str().unwrap());
+        let missing = PazFile { paz_number: 2, ..files[0] };
+
+        let mut output = [0u8; 8];
+        assert!(matches!(
+            archive.read_into(&missing, &mut output),
+            Err(EntryReadError::PazUnavailable { paz_number: 2 })
+        ));
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,46 @@
+use safer_ffi::derive_ReprC;
+
+#[derive_ReprC]
+#[repr(i32)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BlockCompression {
+    /// DXT1: RGB with 1-bit alpha, 8 bytes per 4x4 block.
+    Bc1 = 0,
+    /// DXT5: RGBA, 16 bytes per block.
+    Bc3 = 1,
+    /// Two channels, used for normal maps.
+    Bc5 = 2,
+    Bc7 = 3,
+}
+
+#[derive_ReprC]
+#[repr(i32)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EncodeQuality {
+    Fast = 0,
+    Normal = 1,
+    Slow = 2,
+}
+
+#[derive_ReprC]
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TextureEncodeOptions {
+    pub format: BlockCompression,
+    /// Marks BC1, BC3 and BC7 output as sRGB; ignored for BC5.
+    pub srgb: bool,
+    /// Mip levels to generate, 0 for a full chain down to 1x1.
+    pub mip_count: u32,
+    pub quality: EncodeQuality,
+}
+
+impl Default for TextureEncodeOptions {
+    fn default() -> Self {
+        Self {
+            format: BlockCompression::Bc7,
+            srgb: false,
+            mip_count: 0,
+            quality: EncodeQuality::Normal,
+        }
+    }
+}
@@ -0,0 +1,68 @@
+use safer_ffi::derive_ReprC;
+
+/// Which surfaces of a texture are exported.
+#[derive_ReprC]
+#[repr(i32)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TextureLayout {
+    /// One image for `layer` and `mip`, or one per depth slice for volume textures.
+    Single = 0,
+    /// One image per layer (or cubemap face), depth slice and mip level.
+    AllSeparate = 1,
+    /// The six faces of cube `layer / 6` at `mip`, arranged as a horizontal cross.
+    CubeCross = 2,
+}
+
+#[derive_ReprC]
+#[repr(i32)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TextureFileFormat {
+    Png = 0,
+    Tga = 1,
+    WebP = 2,
+    /// 32-bit float channels, keeping HDR content.
+    Exr = 3,
+}
+
+#[derive_ReprC]
+#[repr(i32)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AlphaMode {
+    Straight = 0,
+    Premultiplied = 1,
+}
+
+#[derive_ReprC]
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TextureExportOptions {
+    pub layout: TextureLayout,
+    /// Array layer; for cubemaps the faces count as layers (+X, -X, +Y, -Y, +Z, -Z per cube).
+    pub layer: u32,
+    pub mip: u32,
+    pub format: TextureFileFormat,
+    pub alpha: AlphaMode,
+}
+
+impl Default for TextureExportOptions {
+    fn default() -> Self {
+        Self {
+            layout: TextureLayout::Single,
+            layer: 0,
+            mip: 0,
+            format: TextureFileFormat::Png,
+            alpha: AlphaMode::Straight,
+        }
+    }
+}
+
+impl TextureFileFormat {
+    pub fn extension(self) -> &'static str {
+        match self {
+            Self::Png => "png",
+            Self::Tga => "tga",
+            Self::WebP => "webp",
+            Self::Exr => "exr",
+        }
+    }
+}
@@ -0,0 +1,25 @@
+use safer_ffi::derive_ReprC;
+use safer_ffi::prelude::repr_c;
+
+/// Small RGBA8 preview of one texture entry. Entries that could not be decoded have zero size
+/// and no pixels.
+#[derive_ReprC]
+#[repr(C)]
+pub struct Thumbnail {
+    pub file_index: u32,
+    pub width: u32,
+    pub height: u32,
+    /// Tightly packed rows of `width * 4` bytes.
+    pub pixels: repr_c::Vec<u8>,
+}
+
+impl Thumbnail {
+    pub fn missing(file_index: u32) -> Self {
+        Self {
+            file_index,
+            width: 0,
+            height: 0,
+            pixels: repr_c::Vec::EMPTY,
+        }
+    }
+}
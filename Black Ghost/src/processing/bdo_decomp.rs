@@ -1,14 +1,79 @@
-﻿#[derive(Debug)]
+﻿use std::fmt;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum BdoDecompError {
-    TruncatedData,
-    CorruptedData,
-    OutputBufferTooSmall,
+    /// The input ended at `input_offset` although `needed` bytes were required there.
+    TruncatedData {
+        input_offset: usize,
+        output_offset: usize,
+        needed: usize,
+        available: usize,
+    },
+    /// A match block points before the start of the output or past its end.
+    CorruptedData {
+        input_offset: usize,
+        output_offset: usize,
+        block_header: u32,
+        distance: usize,
+        length: usize,
+    },
+    OutputBufferTooSmall {
+        needed: usize,
+        available: usize,
+    },
+    /// Strict mode only: decoding stopped before the size announced by the header.
+    SizeMismatch {
+        input_offset: usize,
+        expected: usize,
+        produced: usize,
+    },
+}
+
+impl fmt::Display for BdoDecompError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            Self::TruncatedData { input_offset, output_offset, needed, available } => write!(
+                f,
+                "truncated input at offset {input_offset} (output offset {output_offset}): needed {needed} bytes, {available} available"
+            ),
+            Self::CorruptedData { input_offset, output_offset, block_header, distance, length } => write!(
+                f,
+                "corrupted match block {block_header:#010x} at input offset {input_offset}: distance {distance}, length {length} at output offset {output_offset}"
+            ),
+            Self::OutputBufferTooSmall { needed, available } => {
+                write!(f, "output buffer too small: needed {needed} bytes, {available} available")
+            }
+            Self::SizeMismatch { input_offset, expected, produced } => write!(
+                f,
+                "decompressed {produced} of {expected} bytes, stopped at input offset {input_offset}"
+            ),
+        }
+    }
 }
 
+impl std::error::Error for BdoDecompError {}
+
 pub struct BdoDecomp;
 
 impl BdoDecomp {
+    /// Decompresses a container, returning how many bytes were written.
+    ///
+    /// Truncated compressed streams yield partial output; use `decompress_strict` to reject them.
     pub fn decompress(input: &[u8], output: &mut [u8]) -> Result<u32, BdoDecompError> {
+        Self::decompress_impl::<true>(input, output, false)
+    }
+
+    /// Like `decompress`, but fails unless exactly the announced size was produced.
+    pub fn decompress_strict(input: &[u8], output: &mut [u8]) -> Result<u32, BdoDecompError> {
+        Self::decompress_impl::<true>(input, output, true)
+    }
+
+    /// Reference decoder that copies matches byte by byte; kept to validate the fast path.
+    pub fn decompress_scalar(input: &[u8], output: &mut [u8]) -> Result<u32, BdoDecompError> {
+        Self::decompress_impl::<false>(input, output, false)
+    }
+
+    fn decompress_impl<const FAST_COPY: bool>(input: &[u8], output: &mut [u8], strict: bool) -> Result<u32, BdoDecompError> {
         if input.is_empty() {
             return Ok(0);
         }
@@ -17,28 +82,50 @@ impl BdoDecomp {
         let (target_len, comp_len, header_size) = Self::parse_file_header(input)?;
 
         if input.len() < comp_len {
-            return Err(BdoDecompError::TruncatedData);
+            return Err(BdoDecompError::TruncatedData {
+                input_offset: 0,
+                output_offset: 0,
+                needed: comp_len,
+                available: input.len(),
+            });
         }
         let input = &input[..comp_len];
+        let target = target_len as usize;
+
+        if strict && output.len() < target {
+            return Err(BdoDecompError::OutputBufferTooSmall { needed: target, available: output.len() });
+        }
 
         if (flags & 0x01) == 0 {
-            let copy_len = target_len as usize;
-            if output.len() < copy_len || input.len() < header_size + copy_len {
-                return Err(BdoDecompError::OutputBufferTooSmall);
+            if input.len() < header_size + target {
+                return Err(BdoDecompError::TruncatedData {
+                    input_offset: header_size,
+                    output_offset: 0,
+                    needed: target,
+                    available: input.len().saturating_sub(header_size),
+                });
             }
-            output[..copy_len].copy_from_slice(&input[header_size..header_size + copy_len]);
+            if output.len() < target {
+                return Err(BdoDecompError::OutputBufferTooSmall { needed: target, available: output.len() });
+            }
+            output[..target].copy_from_slice(&input[header_size..header_size + target]);
             return Ok(target_len);
         }
 
-        Self::unpack_core(input, output, target_len, header_size)
+        let (produced, in_idx) = Self::unpack_core::<FAST_COPY>(input, output, target_len, header_size)?;
+        if strict && produced != target {
+            return Err(BdoDecompError::SizeMismatch { input_offset: in_idx, expected: target, produced });
+        }
+
+        Ok(produced as u32)
     }
 
-    fn unpack_core(
+    fn unpack_core<const FAST_COPY: bool>(
         input: &[u8],
         output: &mut [u8],
         target_len: u32,
         start_idx: usize,
-    ) -> Result<u32, BdoDecompError> {
+    ) -> Result<(usize, usize), BdoDecompError> {
         let mut in_idx = start_idx;
         let mut out_idx = 0usize;
         let mut group_header = 1u32;
@@ -72,8 +159,22 @@ impl BdoDecomp {
                 ]);
                 let (dist, len, step) = Self::parse_block_header(header);
 
+                if dist == 0 || out_idx < dist || out_idx + len > output.len() {
+                    return Err(BdoDecompError::CorruptedData {
+                        input_offset: in_idx,
+                        output_offset: out_idx,
+                        block_header: header,
+                        distance: dist,
+                        length: len,
+                    });
+                }
+
                 in_idx += step;
-                Self::copy_match(output, out_idx, dist, len)?;
+                if FAST_COPY {
+                    Self::copy_match(output, out_idx, dist, len);
+                } else {
+                    Self::copy_match_scalar(output, out_idx, dist, len);
+                }
                 out_idx += len;
                 group_header >>= 1;
             } else {
@@ -97,17 +198,101 @@ impl BdoDecomp {
         Self::process_tail(input, output, out_idx, in_idx, target, group_header)
     }
 
+    /// Copies a match with wide loads and stores. Up to one chunk past `out_idx + len` may be
+    /// overwritten, like the 4-byte literal copies, but never past the end of `output`.
+    ///
+    /// Caller guarantees `dist <= out_idx` and `out_idx + len <= output.len()`.
     #[inline(always)]
-    fn copy_match(
-        output: &mut [u8],
-        out_idx: usize,
-        dist: usize,
-        len: usize,
-    ) -> Result<(), BdoDecompError> {
-        if out_idx < dist || out_idx + len > output.len() {
-            return Err(BdoDecompError::CorruptedData);
+    fn copy_match(output: &mut [u8], mut out_idx: usize, mut dist: usize, mut len: usize) {
+        if dist == 1 {
+            let byte = output[out_idx - 1];
+            output[out_idx..out_idx + len].fill(byte);
+            return;
+        }
+
+        if dist < 8 {
+            // Extend the repeating pattern until a multiple of its period reaches 8 bytes;
+            // from there on the data can be copied from that larger distance in whole words.
+            let period = dist * 8usize.div_ceil(dist);
+            let prefix = (period - dist).min(len);
+            Self::copy_match_scalar(output, out_idx, dist, prefix);
+
+            out_idx += prefix;
+            len -= prefix;
+            dist = period;
+            if len == 0 {
+                return;
+            }
+        }
+
+        #[cfg(target_arch = "x86_64")]
+        if dist >= 32 && Self::has_avx2() {
+            let done = unsafe { Self::copy_chunks_avx2(output, out_idx, dist, len) };
+            if done < len {
+                Self::copy_match_scalar(output, out_idx + done, dist, len - done);
+            }
+            return;
+        }
+
+        let done = if dist >= 16 {
+            Self::copy_chunks::<u128>(output, out_idx, dist, len)
+        } else {
+            Self::copy_chunks::<u64>(output, out_idx, dist, len)
+        };
+        if done < len {
+            Self::copy_match_scalar(output, out_idx + done, dist, len - done);
         }
+    }
+
+    #[cfg(target_arch = "x86_64")]
+    #[inline(always)]
+    fn has_avx2() -> bool {
+        std::arch::is_x86_feature_detected!("avx2")
+    }
+
+    /// Copies whole `T` sized chunks while they fit into `output`; `dist` must be at least
+    /// `size_of::<T>()`. Returns how many bytes of the match were produced.
+    #[inline(always)]
+    fn copy_chunks<T: Copy>(output: &mut [u8], out_idx: usize, dist: usize, len: usize) -> usize {
+        let width = size_of::<T>();
+        debug_assert!(dist >= width);
 
+        let mut done = 0;
+        unsafe {
+            let base = output.as_mut_ptr();
+            while done < len && out_idx + done + width <= output.len() {
+                let dst = base.add(out_idx + done);
+                let src = dst.sub(dist);
+                std::ptr::write_unaligned(dst as *mut T, std::ptr::read_unaligned(src as *const T));
+                done += width;
+            }
+        }
+        done.min(len)
+    }
+
+    #[cfg(target_arch = "x86_64")]
+    #[target_feature(enable = "avx2")]
+    unsafe fn copy_chunks_avx2(output: &mut [u8], out_idx: usize, dist: usize, len: usize) -> usize {
+        use std::arch::x86_64::{__m256i, _mm256_loadu_si256, _mm256_storeu_si256};
+
+        let mut done = 0;
+        unsafe {
+            let base = output.as_mut_ptr();
+            while done < len && out_idx + done + 32 <= output.len() {
+                let dst = base.add(out_idx + done);
+                let src = dst.sub(dist);
+                _mm256_storeu_si256(dst as *mut __m256i, _mm256_loadu_si256(src as *const __m256i));
+                done += 32;
+            }
+        }
+        done.min(len)
+    }
+
+    /// Byte by byte reference copy, correct for any overlap.
+    ///
+    /// Caller guarantees `dist <= out_idx` and `out_idx + len <= output.len()`.
+    #[inline(always)]
+    fn copy_match_scalar(output: &mut [u8], out_idx: usize, dist: usize, len: usize) {
         unsafe {
             let ptr = output.as_mut_ptr().add(out_idx);
             let mut src = ptr.offset(-(dist as isize));
@@ -119,11 +304,10 @@ impl BdoDecomp {
                 src = src.add(1);
             }
         }
-        Ok(())
     }
 
     #[inline(always)]
-    fn get_literal_length(group_header: u32) -> usize {
+    pub(crate) fn get_literal_length(group_header: u32) -> usize {
         static TABLE: [u8; 16] = [4, 0, 1, 0, 2, 0, 1, 0, 3, 0, 1, 0, 2, 0, 1, 0];
         TABLE[(group_header & 0xF) as usize] as usize
     }
@@ -135,7 +319,7 @@ impl BdoDecomp {
         mut in_idx: usize,
         target: usize,
         mut group: u32,
-    ) -> Result<u32, BdoDecompError> {
+    ) -> Result<(usize, usize), BdoDecompError> {
         let input_len = input.len();
 
         while out_idx < target {
@@ -162,11 +346,11 @@ impl BdoDecomp {
             }
         }
 
-        Ok(out_idx as u32)
+        Ok((out_idx, in_idx))
     }
 
     #[inline(always)]
-    fn parse_block_header(h: u32) -> (usize, usize, usize) {
+    pub(crate) fn parse_block_header(h: u32) -> (usize, usize, usize) {
         if (h & 0x03) == 0x03 {
             if (h & 0x7F) == 3 {
                 ((h >> 15) as usize, ((h >> 7) & 0xFF) as usize + 3, 4)
@@ -187,10 +371,10 @@ impl BdoDecomp {
     }
 
     #[inline(always)]
-    fn parse_file_header(input: &[u8]) -> Result<(u32, usize, usize), BdoDecompError> {
+    pub fn parse_file_header(input: &[u8]) -> Result<(u32, usize, usize), BdoDecompError> {
         if (input[0] & 0x02) != 0 {
             if input.len() < 9 {
-                return Err(BdoDecompError::TruncatedData);
+                return Err(Self::truncated_header(9, input.len()));
             }
             let comp_len = u32::from_le_bytes([input[1], input[2], input[3], input[4]]) as usize;
             let decomp_len = u32::from_le_bytes([input[5], input[6], input[7], input[8]]);
@@ -198,7 +382,7 @@ impl BdoDecomp {
             Ok((decomp_len, comp_len, 9))
         } else {
             if input.len() < 3 {
-                return Err(BdoDecompError::TruncatedData);
+                return Err(Self::truncated_header(3, input.len()));
             }
             let comp_len = input[1] as usize;
             let decomp_len = input[2] as u32;
@@ -206,4 +390,100 @@ impl BdoDecomp {
             Ok((decomp_len, comp_len, 3))
         }
     }
+
+    fn truncated_header(needed: usize, available: usize) -> BdoDecompError {
+        BdoDecompError::TruncatedData { input_offset: 0, output_offset: 0, needed, available }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::processing::test_support::{compress_ops, random_ops, Lcg};
+
+    #[test]
+    fn fast_match_copy_matches_scalar_reference() {
+        let mut rng = Lcg(0xC0FFEE);
+        for _ in 0..20_000 {
+            let size = 64 + rng.below(512);
+            let mut seed_data = vec![0u8; size];
+            seed_data.iter_mut().for_each(|b| *b = rng.next() as u8);
+
+            let out_idx = 1 + rng.below(size / 2);
+            let dist = 1 + rng.below(out_idx.min(80));
+            let len = rng.below((size - out_idx).min(300) + 1);
+
+            let mut scalar = seed_data.clone();
+            let mut fast = seed_data.clone();
+            BdoDecomp::copy_match_scalar(&mut scalar, out_idx, dist, len);
+            BdoDecomp::copy_match(&mut fast, out_idx, dist, len);
+
+            assert_eq!(&fast[..out_idx + len], &scalar[..out_idx + len], "dist {dist} len {len} at {out_idx}");
+        }
+    }
+
+    #[test]
+    fn fast_decompression_matches_scalar_reference() {
+        let mut rng = Lcg(0xD1FF);
+        for round in 0..32 {
+            let (input, expected) = compress_ops(&random_ops(&mut rng, 100 + round * 700), true);
+
+            let mut fast = vec![0u8; expected.len()];
+            let mut scalar = vec![0u8; expected.len()];
+            let fast_len = BdoDecomp::decompress(&input, &mut fast).unwrap();
+            let scalar_len = BdoDecomp::decompress_scalar(&input, &mut scalar).unwrap();
+
+            assert_eq!(fast_len, scalar_len);
+            assert_eq!(fast, scalar);
+            assert_eq!(fast, expected);
+        }
+    }
+
+    #[test]
+    fn strict_mode_rejects_partial_output() {
+        let mut rng = Lcg(11);
+        let (mut input, expected) = compress_ops(&random_ops(&mut rng, 2000), true);
+
+        // Announce a shorter stream so decoding runs out of input early.
+        let short_len = input.len() - 8;
+        input[1..5].copy_from_slice(&(short_len as u32).to_le_bytes());
+
+        let mut output = vec![0u8; expected.len()];
+        let produced = BdoDecomp::decompress(&input, &mut output).unwrap() as usize;
+        assert!(produced < expected.len());
+
+        match BdoDecomp::decompress_strict(&input, &mut output) {
+            Err(BdoDecompError::SizeMismatch { expected: e, produced: p, input_offset }) => {
+                assert_eq!((e, p), (expected.len(), produced));
+                assert_eq!(input_offset, short_len);
+            }
+            other => panic!("unexpected result {:?}", other),
+        }
+    }
+
+    #[test]
+    fn corrupted_match_reports_its_position() {
+        // One literal followed by a match reaching 5 bytes back.
+        let input = [0x6D, 13, 6, 0x02, 0x00, 0x00, 0x80, b'a', 5 << 2, 0, 0, 0, 0];
+        let mut output = [0u8; 6];
+
+        match BdoDecomp::decompress(&input, &mut output) {
+            Err(BdoDecompError::CorruptedData { input_offset, output_offset, distance, length, .. }) => {
+                assert_eq!((input_offset, output_offset, distance, length), (8, 1, 5, 3));
+            }
+            other => panic!("unexpected result {:?}", other),
+        }
+    }
+
+    #[test]
+    fn strict_mode_requires_full_output_buffer() {
+        let mut rng = Lcg(5);
+        let (input, expected) = compress_ops(&random_ops(&mut rng, 100), true);
+        let mut output = vec![0u8; expected.len() - 1];
+
+        assert_eq!(
+            BdoDecomp::decompress_strict(&input, &mut output),
+            Err(BdoDecompError::OutputBufferTooSmall { needed: expected.len(), available: expected.len() - 1 })
+        );
+    }
 }
@@ -0,0 +1,367 @@
+use crate::processing::bdo_decomp::{BdoDecomp, BdoDecompError};
+
+/// Largest match distance `BdoDecomp::parse_block_header` can produce is 17 bits.
+pub const WINDOW_SIZE: usize = 1 << 17;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Stage {
+    Header,
+    Stored,
+    Main,
+    Tail,
+    Done,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct FileHeader {
+    flags: u8,
+    target: usize,
+    comp_len: usize,
+}
+
+/// Incremental counterpart of `BdoDecomp::decompress`.
+///
+/// Input is fed in arbitrary chunks and decoded bytes are appended to the caller's buffer as
+/// soon as they are known. Only the last `WINDOW_SIZE` output bytes are retained for matches.
+#[derive(Clone)]
+pub struct BdoStreamDecoder {
+    stage: Stage,
+    header: Option<FileHeader>,
+    pending: Vec<u8>,
+    cursor: usize,
+    in_idx: usize,
+    out_idx: usize,
+    group: u32,
+    history: Vec<u8>,
+}
+
+impl Default for BdoStreamDecoder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl BdoStreamDecoder {
+    pub fn new() -> Self {
+        Self {
+            stage: Stage::Header,
+            header: None,
+            pending: Vec::new(),
+            cursor: 0,
+            in_idx: 0,
+            out_idx: 0,
+            group: 1,
+            history: Vec::new(),
+        }
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.stage == Stage::Done
+    }
+
+    /// Decompressed length announced by the container header, once it has been read.
+    pub fn target_len(&self) -> Option<u32> {
+        self.header.map(|h| h.target as u32)
+    }
+
+    /// Total number of bytes emitted so far.
+    pub fn total_out(&self) -> usize {
+        self.out_idx
+    }
+
+    /// Consumes `input` and appends every byte that can be decoded so far to `output`.
+    pub fn feed(&mut self, input: &[u8], output: &mut Vec<u8>) -> Result<(), BdoDecompError> {
+        if self.stage == Stage::Done {
+            return Ok(());
+        }
+
+        if self.cursor > 0 && self.cursor >= self.pending.len() / 2 {
+            self.pending.drain(..self.cursor);
+            self.cursor = 0;
+        }
+        self.pending.extend_from_slice(input);
+
+        loop {
+            let progressed = match self.stage {
+                Stage::Header => self.step_header()?,
+                Stage::Stored => self.step_stored(output),
+                Stage::Main => self.step_main(output)?,
+                Stage::Tail => self.step_tail(output),
+                Stage::Done => false,
+            };
+            if !progressed {
+                break;
+            }
+        }
+
+        self.trim_history();
+        Ok(())
+    }
+
+    /// Ends the stream. Fails if the container announced more input than was fed.
+    pub fn finish(&self) -> Result<u32, BdoDecompError> {
+        if self.stage == Stage::Done {
+            return Ok(self.out_idx as u32);
+        }
+
+        let needed = match self.header {
+            Some(header) => header.comp_len.saturating_sub(self.in_idx),
+            None => 1,
+        };
+        Err(BdoDecompError::TruncatedData {
+            input_offset: self.in_idx,
+            output_offset: self.out_idx,
+            needed,
+            available: self.available(),
+        })
+    }
+
+    /// Like `finish`, but also fails if the stream ended short of the announced size.
+    pub fn finish_strict(&self) -> Result<u32, BdoDecompError> {
+        let produced = self.finish()? as usize;
+        let expected = self.header.map_or(0, |h| h.target);
+        if produced != expected {
+            return Err(BdoDecompError::SizeMismatch { input_offset: self.in_idx, expected, produced });
+        }
+        Ok(produced as u32)
+    }
+
+    #[inline(always)]
+    fn available(&self) -> usize {
+        self.pending.len() - self.cursor
+    }
+
+    #[inline(always)]
+    fn peek_u32(&self) -> u32 {
+        let p = &self.pending[self.cursor..self.cursor + 4];
+        u32::from_le_bytes([p[0], p[1], p[2], p[3]])
+    }
+
+    #[inline(always)]
+    fn advance(&mut self, count: usize) {
+        self.cursor += count;
+        self.in_idx += count;
+    }
+
+    fn emit(&mut self, byte: u8, output: &mut Vec<u8>) {
+        self.history.push(byte);
+        output.push(byte);
+        self.out_idx += 1;
+    }
+
+    fn trim_history(&mut self) {
+        if self.history.len() > 2 * WINDOW_SIZE {
+            self.history.drain(..self.history.len() - WINDOW_SIZE);
+        }
+    }
+
+    fn step_header(&mut self) -> Result<bool, BdoDecompError> {
+        if self.available() == 0 {
+            return Ok(false);
+        }
+
+        let header_size = if (self.pending[self.cursor] & 0x02) != 0 { 9 } else { 3 };
+        if self.available() < header_size {
+            return Ok(false);
+        }
+
+        let raw = &self.pending[self.cursor..self.cursor + header_size];
+        let (target, comp_len, header_size) = BdoDecomp::parse_file_header(raw)?;
+        let header = FileHeader {
+            flags: raw[0],
+            target: target as usize,
+            comp_len,
+        };
+        if (header.flags & 0x01) == 0 && comp_len < header_size + header.target {
+            return Err(BdoDecompError::TruncatedData {
+                input_offset: self.in_idx + header_size,
+                output_offset: 0,
+                needed: header.target,
+                available: comp_len.saturating_sub(header_size),
+            });
+        }
+
+        self.header = Some(header);
+        self.advance(header_size);
+        self.stage = if (header.flags & 0x01) == 0 { Stage::Stored } else { Stage::Main };
+        Ok(true)
+    }
+
+    fn step_stored(&mut self, output: &mut Vec<u8>) -> bool {
+        let header = self.header.unwrap();
+        let remaining = header.target - self.out_idx;
+        let count = remaining.min(self.available());
+
+        output.extend_from_slice(&self.pending[self.cursor..self.cursor + count]);
+        self.advance(count);
+        self.out_idx += count;
+
+        if self.out_idx == header.target {
+            self.stage = Stage::Done;
+        }
+        count > 0
+    }
+
+    fn step_main(&mut self, output: &mut Vec<u8>) -> Result<bool, BdoDecompError> {
+        let FileHeader { target, comp_len, .. } = self.header.unwrap();
+
+        loop {
+            if self.out_idx >= target || self.in_idx >= comp_len {
+                self.stage = Stage::Tail;
+                return Ok(true);
+            }
+
+            if self.group == 1 {
+                if self.in_idx + 4 > comp_len {
+                    self.stage = Stage::Tail;
+                    return Ok(true);
+                }
+                if self.available() < 4 {
+                    return Ok(false);
+                }
+                self.group = self.peek_u32();
+                self.advance(4);
+            }
+
+            if self.in_idx + 4 > comp_len {
+                self.stage = Stage::Tail;
+                return Ok(true);
+            }
+
+            if (self.group & 1) != 0 {
+                if self.available() < 4 {
+                    return Ok(false);
+                }
+                let block_header = self.peek_u32();
+                let (dist, len, step) = BdoDecomp::parse_block_header(block_header);
+                if dist == 0 || self.out_idx < dist || self.out_idx + len > target {
+                    return Err(BdoDecompError::CorruptedData {
+                        input_offset: self.in_idx,
+                        output_offset: self.out_idx,
+                        block_header,
+                        distance: dist,
+                        length: len,
+                    });
+                }
+
+                self.advance(step);
+                let start = self.history.len() - dist;
+                for i in 0..len {
+                    let byte = self.history[start + i];
+                    self.emit(byte, output);
+                }
+                self.group >>= 1;
+            } else {
+                let lit_len = BdoDecomp::get_literal_length(self.group);
+                if self.out_idx + 4 > target {
+                    self.stage = Stage::Tail;
+                    return Ok(true);
+                }
+                if self.available() < 4 {
+                    return Ok(false);
+                }
+
+                for i in 0..lit_len {
+                    let byte = self.pending[self.cursor + i];
+                    self.emit(byte, output);
+                }
+                self.advance(lit_len);
+                self.group >>= lit_len;
+            }
+
+            if self.history.len() > 2 * WINDOW_SIZE {
+                self.trim_history();
+            }
+        }
+    }
+
+    fn step_tail(&mut self, output: &mut Vec<u8>) -> bool {
+        let FileHeader { target, comp_len, .. } = self.header.unwrap();
+
+        while self.out_idx < target {
+            if self.group == 1 {
+                if self.in_idx + 4 <= comp_len {
+                    if self.available() < 4 {
+                        return false;
+                    }
+                    self.advance(4);
+                }
+                self.group = 0x8000_0000;
+            }
+
+            if self.in_idx >= comp_len {
+                break;
+            }
+            if self.available() == 0 {
+                return false;
+            }
+
+            let byte = self.pending[self.cursor];
+            self.emit(byte, output);
+            self.advance(1);
+            self.group >>= 1;
+        }
+
+        self.stage = Stage::Done;
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::processing::test_support::{compress_ops, random_ops, Lcg};
+
+    fn decode_in_chunks(input: &[u8], chunk_size: usize) -> Vec<u8> {
+        let mut decoder = BdoStreamDecoder::new();
+        let mut output = Vec::new();
+        for chunk in input.chunks(chunk_size) {
+            decoder.feed(chunk, &mut output).unwrap();
+        }
+        assert_eq!(decoder.finish().unwrap() as usize, output.len());
+        output
+    }
+
+    #[test]
+    fn matches_batch_decompression_for_any_chunking() {
+        let mut rng = Lcg(0x5EED);
+        for round in 0..24 {
+            let ops = random_ops(&mut rng, 200 + round * 400);
+            let (input, expected) = compress_ops(&ops, true);
+
+            let mut batch = vec![0u8; expected.len()];
+            let written = BdoDecomp::decompress(&input, &mut batch).unwrap() as usize;
+            assert_eq!(&batch[..written], &expected[..]);
+
+            for chunk_size in [1, 3, 7, 64, 4096, input.len()] {
+                assert_eq!(decode_in_chunks(&input, chunk_size), expected, "chunk size {chunk_size}");
+            }
+        }
+    }
+
+    #[test]
+    fn keeps_window_bounded_on_long_outputs() {
+        let mut rng = Lcg(42);
+        let ops = random_ops(&mut rng, 400_000);
+        let (input, expected) = compress_ops(&ops, true);
+
+        let mut decoder = BdoStreamDecoder::new();
+        let mut output = Vec::new();
+        for chunk in input.chunks(8192) {
+            decoder.feed(chunk, &mut output).unwrap();
+            assert!(decoder.history.len() <= 2 * WINDOW_SIZE);
+        }
+        assert_eq!(output, expected);
+    }
+
+    #[test]
+    fn reports_truncated_input() {
+        let mut rng = Lcg(7);
+        let (input, _) = compress_ops(&random_ops(&mut rng, 500), true);
+
+        let mut decoder = BdoStreamDecoder::new();
+        let mut output = Vec::new();
+        decoder.feed(&input[..input.len() / 2], &mut output).unwrap();
+        assert!(matches!(decoder.finish(), Err(BdoDecompError::TruncatedData { .. })));
+    }
+}
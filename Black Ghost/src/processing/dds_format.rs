@@ -0,0 +1,108 @@
+use image_dds::ddsfile::{Dds, DxgiFormat};
+use image_dds::ImageFormat;
+
+/// Describes the pixel format of DDS files the same way regardless of header flavour.
+pub struct DdsFormat;
+
+impl DdsFormat {
+    /// DXGI format of `dds`. Legacy headers without a DX10 extension are mapped through the
+    /// format their FourCC or D3D format decodes as; `None` if there is no DXGI equivalent.
+    pub fn dxgi(dds: &Dds) -> Option<DxgiFormat> {
+        dds.get_dxgi_format()
+            .or_else(|| image_dds::dds_image_format(dds).ok().and_then(Self::dxgi_from_image_format))
+    }
+
+    /// FourCC code of the pixel format, or 0 if the header does not use one.
+    pub fn fourcc(dds: &Dds) -> u32 {
+        dds.header.spf.fourcc.as_ref().map_or(0, |fourcc| fourcc.0)
+    }
+
+    /// Block edge length in pixels and bytes per block; uncompressed formats use 1x1 blocks.
+    pub fn block_layout(format: ImageFormat) -> Option<(u32, u32)> {
+        Some(match format {
+            ImageFormat::BC1RgbaUnorm
+            | ImageFormat::BC1RgbaUnormSrgb
+            | ImageFormat::BC4RUnorm
+            | ImageFormat::BC4RSnorm => (4, 8),
+            ImageFormat::BC2RgbaUnorm
+            | ImageFormat::BC2RgbaUnormSrgb
+            | ImageFormat::BC3RgbaUnorm
+            | ImageFormat::BC3RgbaUnormSrgb
+            | ImageFormat::BC5RgUnorm
+            | ImageFormat::BC5RgSnorm
+            | ImageFormat::BC6hRgbUfloat
+            | ImageFormat::BC6hRgbSfloat
+            | ImageFormat::BC7RgbaUnorm
+            | ImageFormat::BC7RgbaUnormSrgb => (4, 16),
+            ImageFormat::R8Unorm | ImageFormat::R8Snorm => (1, 1),
+            ImageFormat::Rg8Unorm
+            | ImageFormat::Rg8Snorm
+            | ImageFormat::Bgra4Unorm
+            | ImageFormat::Bgr5A1Unorm
+            | ImageFormat::R16Unorm
+            | ImageFormat::R16Snorm
+            | ImageFormat::R16Float => (1, 2),
+            ImageFormat::Bgr8Unorm => (1, 3),
+            ImageFormat::Rgba8Unorm
+            | ImageFormat::Rgba8UnormSrgb
+            | ImageFormat::Rgba8Snorm
+            | ImageFormat::Bgra8Unorm
+            | ImageFormat::Bgra8UnormSrgb
+            | ImageFormat::Rg16Unorm
+            | ImageFormat::Rg16Snorm
+            | ImageFormat::Rg16Float
+            | ImageFormat::R32Float => (1, 4),
+            ImageFormat::Rgba16Unorm
+            | ImageFormat::Rgba16Snorm
+            | ImageFormat::Rgba16Float
+            | ImageFormat::Rg32Float => (1, 8),
+            ImageFormat::Rgb32Float => (1, 12),
+            ImageFormat::Rgba32Float => (1, 16),
+            _ => return None,
+        })
+    }
+
+    pub fn dxgi_from_image_format(format: ImageFormat) -> Option<DxgiFormat> {
+        Some(match format {
+            ImageFormat::BC1RgbaUnorm => DxgiFormat::BC1_UNorm,
+            ImageFormat::BC1RgbaUnormSrgb => DxgiFormat::BC1_UNorm_sRGB,
+            ImageFormat::BC2RgbaUnorm => DxgiFormat::BC2_UNorm,
+            ImageFormat::BC2RgbaUnormSrgb => DxgiFormat::BC2_UNorm_sRGB,
+            ImageFormat::BC3RgbaUnorm => DxgiFormat::BC3_UNorm,
+            ImageFormat::BC3RgbaUnormSrgb => DxgiFormat::BC3_UNorm_sRGB,
+            ImageFormat::BC4RUnorm => DxgiFormat::BC4_UNorm,
+            ImageFormat::BC4RSnorm => DxgiFormat::BC4_SNorm,
+            ImageFormat::BC5RgUnorm => DxgiFormat::BC5_UNorm,
+            ImageFormat::BC5RgSnorm => DxgiFormat::BC5_SNorm,
+            ImageFormat::BC6hRgbUfloat => DxgiFormat::BC6H_UF16,
+            ImageFormat::BC6hRgbSfloat => DxgiFormat::BC6H_SF16,
+            ImageFormat::BC7RgbaUnorm => DxgiFormat::BC7_UNorm,
+            ImageFormat::BC7RgbaUnormSrgb => DxgiFormat::BC7_UNorm_sRGB,
+            ImageFormat::R8Unorm => DxgiFormat::R8_UNorm,
+            ImageFormat::R8Snorm => DxgiFormat::R8_SNorm,
+            ImageFormat::Rg8Unorm => DxgiFormat::R8G8_UNorm,
+            ImageFormat::Rg8Snorm => DxgiFormat::R8G8_SNorm,
+            ImageFormat::Rgba8Unorm => DxgiFormat::R8G8B8A8_UNorm,
+            ImageFormat::Rgba8UnormSrgb => DxgiFormat::R8G8B8A8_UNorm_sRGB,
+            ImageFormat::Rgba8Snorm => DxgiFormat::R8G8B8A8_SNorm,
+            ImageFormat::Rgba16Float => DxgiFormat::R16G16B16A16_Float,
+            ImageFormat::Rgba32Float => DxgiFormat::R32G32B32A32_Float,
+            ImageFormat::Bgra8Unorm => DxgiFormat::B8G8R8A8_UNorm,
+            ImageFormat::Bgra8UnormSrgb => DxgiFormat::B8G8R8A8_UNorm_sRGB,
+            ImageFormat::Bgra4Unorm => DxgiFormat::B4G4R4A4_UNorm,
+            ImageFormat::Bgr5A1Unorm => DxgiFormat::B5G5R5A1_UNorm,
+            ImageFormat::R16Unorm => DxgiFormat::R16_UNorm,
+            ImageFormat::R16Snorm => DxgiFormat::R16_SNorm,
+            ImageFormat::Rg16Unorm => DxgiFormat::R16G16_UNorm,
+            ImageFormat::Rg16Snorm => DxgiFormat::R16G16_SNorm,
+            ImageFormat::Rgba16Unorm => DxgiFormat::R16G16B16A16_UNorm,
+            ImageFormat::Rgba16Snorm => DxgiFormat::R16G16B16A16_SNorm,
+            ImageFormat::R16Float => DxgiFormat::R16_Float,
+            ImageFormat::Rg16Float => DxgiFormat::R16G16_Float,
+            ImageFormat::R32Float => DxgiFormat::R32_Float,
+            ImageFormat::Rg32Float => DxgiFormat::R32G32_Float,
+            ImageFormat::Rgb32Float => DxgiFormat::R32G32B32_Float,
+            _ => return None,
+        })
+    }
+}
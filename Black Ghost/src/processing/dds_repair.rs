@@ -0,0 +1,478 @@
+use std::fmt;
+use std::io::Cursor;
+use image_dds::ddsfile::Dds;
+use image_dds::{mip_dimension, Surface};
+use crate::processing::dds_format::DdsFormat;
+
+const MAGIC: &[u8; 4] = b"DDS ";
+const HEADER_SIZE: usize = 124;
+const DX10_HEADER_SIZE: usize = 20;
+const FOURCC_OFFSET: usize = 80;
+const CAPS_OFFSET: usize = 104;
+const DDSCAPS_COMPLEX: u32 = 0x8;
+const DDSCAPS_TEXTURE: u32 = 0x1000;
+const DDSCAPS_MIPMAP: u32 = 0x40_0000;
+const D3D10_RESOURCE_DIMENSION_TEXTURE2D: u32 = 3;
+/// Trailing bytes still accepted after a chain with stripped top mips, e.g. 16-byte padding.
+const STRIPPED_TAIL_SLACK: u64 = 16;
+
+/// Bytes `read_header` needs at most: magic, header and DX10 extension.
+pub const MAX_HEADER_LEN: usize = 4 + HEADER_SIZE + DX10_HEADER_SIZE;
+
+/// Why texture data could not be turned into a decodable DDS file.
+#[derive(Debug)]
+pub enum DdsRepairError {
+    /// Neither a `DDS ` magic nor a bare header was found; the data has no header at all.
+    /// Format, size and mip count cannot be recovered from pixel data alone.
+    MissingHeader { len: usize },
+    /// The header itself is cut short, and too little of it survives to rebuild it.
+    TruncatedHeader { needed: usize, available: usize },
+    /// `ddsfile` rejected the header.
+    InvalidHeader(image_dds::ddsfile::Error),
+    ZeroSized { width: u32, height: u32 },
+    /// The pixel format is not known to the decoder.
+    UnsupportedFormat { fourcc: u32, dxgi: u32 },
+    /// Not even the smallest declared mip level is present.
+    NotEnoughData { needed: u64, available: usize },
+}
+
+impl fmt::Display for DdsRepairError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::MissingHeader { len } => write!(f, "no DDS header found in {len} bytes"),
+            Self::TruncatedHeader { needed, available } => {
+                write!(f, "DDS header truncated: needed {needed} bytes, {available} available")
+            }
+            Self::InvalidHeader(e) => write!(f, "invalid DDS header: {e}"),
+            Self::ZeroSized { width, height } => write!(f, "texture has no pixels ({width} x {height})"),
+            Self::UnsupportedFormat { fourcc, dxgi } => {
+                write!(f, "unsupported pixel format (FourCC {fourcc:#010x}, DXGI {dxgi})")
+            }
+            Self::NotEnoughData { needed, available } => write!(
+                f,
+                "pixel data too short: smallest usable mip chain needs {needed} bytes, {available} available"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for DdsRepairError {}
+
+/// What had to be changed to make the data decodable. All zero/false for regular files.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct DdsRepairReport {
+    /// The header was present but the `DDS ` magic in front of it was not.
+    pub missing_magic: bool,
+    /// The header was cut short in front of the pixel data and its tail was filled in.
+    pub rebuilt_header: bool,
+    pub declared_mips: u32,
+    /// Mip levels the data actually holds; lower than declared for cut-off mip chains.
+    pub kept_mips: u32,
+    /// Largest mip levels that were dropped from the data, so the header was shrunk to match.
+    pub stripped_top_mips: u32,
+}
+
+impl DdsRepairReport {
+    pub fn is_modified(&self) -> bool {
+        self.missing_magic || self.rebuilt_header || self.kept_mips != self.declared_mips || self.stripped_top_mips > 0
+    }
+}
+
+/// Reads `.dds` and `.dds1` entries, including variants the game ships with a missing magic,
+/// a header cut short in front of the pixel data, a mip count larger than the stored chain,
+/// or with the largest mips stripped.
+///
+/// A cut header is only rebuilt while its dimensions, mip count and pixel format survive (the
+/// DXGI format too for `DX10` files); the missing caps are regenerated for a plain 2D texture.
+/// Pixel data without any header is reported as `MissingHeader`: the index holds no format or
+/// dimensions to rebuild it from.
+pub struct DdsRepair;
+
+impl DdsRepair {
+    pub fn read(data: &[u8]) -> Result<(Dds, DdsRepairReport), DdsRepairError> {
+        let parsed = Self::read_header(data);
+        let complete = match &parsed {
+            Ok((dds, missing_magic)) => {
+                Self::holds_full_chain(dds) || Self::has_header_tail(&data[if *missing_magic { 0 } else { 4 }..])
+            }
+            Err(e) => !matches!(e, DdsRepairError::TruncatedHeader { .. } | DdsRepairError::InvalidHeader(_)),
+        };
+
+        if !complete && let Some((mut dds, missing_magic)) = Self::rebuild_header(data) {
+            let mut report = DdsRepairReport { missing_magic, rebuilt_header: true, ..Default::default() };
+            Self::fit_mip_chain(&mut dds, &mut report)?;
+            return Ok((dds, report));
+        }
+
+        let (mut dds, missing_magic) = parsed?;
+        let mut report = DdsRepairReport { missing_magic, ..Default::default() };
+
+        Self::fit_mip_chain(&mut dds, &mut report)?;
+        Ok((dds, report))
+    }
+
+    /// Parses only the header, so `data` may stop right after it. Also returns whether the
+    /// `DDS ` magic was missing.
+    pub fn read_header(data: &[u8]) -> Result<(Dds, bool), DdsRepairError> {
+        let (header_start, missing_magic) = if data.starts_with(MAGIC) {
+            (4, false)
+        } else if data.len() >= 4 && u32::from_le_bytes(data[..4].try_into().unwrap()) == HEADER_SIZE as u32 {
+            (0, true)
+        } else {
+            return Err(DdsRepairError::MissingHeader { len: data.len() });
+        };
+
+        let needed = Self::header_len(data, header_start);
+        if data.len() < needed {
+            return Err(DdsRepairError::TruncatedHeader { needed, available: data.len() });
+        }
+
+        let dds = if missing_magic {
+            let mut fixed = Vec::with_capacity(data.len() + 4);
+            fixed.extend_from_slice(MAGIC);
+            fixed.extend_from_slice(data);
+            Dds::read(&mut Cursor::new(fixed))
+        } else {
+            Dds::read(&mut Cursor::new(data))
+        }
+        .map_err(DdsRepairError::InvalidHeader)?;
+
+        Ok((dds, missing_magic))
+    }
+
+    /// Fills in the tail of a header that stops early, followed directly by a complete mip
+    /// chain. The chain size follows from the surviving fields, so whatever the data holds
+    /// beyond it must be the part of the header that survived.
+    fn rebuild_header(data: &[u8]) -> Option<(Dds, bool)> {
+        let missing_magic = !data.starts_with(MAGIC);
+        let body = &data[if missing_magic { 0 } else { 4 }..];
+        if missing_magic && body.get(..4) != Some(&(HEADER_SIZE as u32).to_le_bytes()[..]) {
+            return None;
+        }
+
+        let is_dx10 = body.get(FOURCC_OFFSET..FOURCC_OFFSET + 4) == Some(b"DX10");
+        let (required, full) = if is_dx10 {
+            (HEADER_SIZE + 4, HEADER_SIZE + DX10_HEADER_SIZE)
+        } else {
+            (CAPS_OFFSET, HEADER_SIZE)
+        };
+        if body.len() < required {
+            return None;
+        }
+
+        let mut header = Vec::with_capacity(4 + full);
+        header.extend_from_slice(MAGIC);
+        header.extend_from_slice(&body[..required]);
+        header.resize(4 + full, 0);
+
+        let mips = u32::from_le_bytes(body[24..28].try_into().unwrap());
+        let mut caps = DDSCAPS_TEXTURE;
+        if mips > 1 {
+            caps |= DDSCAPS_COMPLEX | DDSCAPS_MIPMAP;
+        }
+        header[4 + CAPS_OFFSET..4 + CAPS_OFFSET + 4].copy_from_slice(&caps.to_le_bytes());
+        if is_dx10 {
+            let dx10 = 4 + HEADER_SIZE;
+            header[dx10 + 4..dx10 + 8].copy_from_slice(&D3D10_RESOURCE_DIMENSION_TEXTURE2D.to_le_bytes());
+            header[dx10 + 12..dx10 + 16].copy_from_slice(&1u32.to_le_bytes());
+        }
+
+        let probe = Dds::read(&mut Cursor::new(&header)).ok()?;
+        let (layers, mip_sizes) = Self::mip_sizes(&probe).ok()?;
+        let chain = (layers * mip_sizes.iter().sum::<u64>()) as usize;
+        let kept = body.len().checked_sub(chain)?;
+        if kept < required || kept >= full {
+            return None;
+        }
+
+        header.extend_from_slice(&body[kept..]);
+        let dds = Dds::read(&mut Cursor::new(header)).ok()?;
+        Some((dds, missing_magic))
+    }
+
+    /// Whether the fields behind the pixel format look like a written header rather than pixel
+    /// data: the required texture cap is set and the unused caps are zero. Keeps cut-off mip
+    /// chains a few bytes short from being mistaken for a cut header.
+    fn has_header_tail(body: &[u8]) -> bool {
+        let Some(tail) = body.get(CAPS_OFFSET..HEADER_SIZE) else {
+            return false;
+        };
+        let caps = u32::from_le_bytes(tail[..4].try_into().unwrap());
+        caps & DDSCAPS_TEXTURE != 0 && tail[8..].iter().all(|&b| b == 0)
+    }
+
+    fn holds_full_chain(dds: &Dds) -> bool {
+        Self::mip_sizes(dds)
+            .is_ok_and(|(layers, mip_sizes)| layers * mip_sizes.iter().sum::<u64>() <= dds.data.len() as u64)
+    }
+
+    /// Layer count and the bytes of every declared mip level of one layer.
+    fn mip_sizes(dds: &Dds) -> Result<(u64, Vec<u64>), DdsRepairError> {
+        let (width, height, depth) = (dds.get_width(), dds.get_height(), dds.get_depth());
+        if width == 0 || height == 0 {
+            return Err(DdsRepairError::ZeroSized { width, height });
+        }
+
+        let unsupported = || DdsRepairError::UnsupportedFormat {
+            fourcc: DdsFormat::fourcc(dds),
+            dxgi: dds.get_dxgi_format().map_or(0, |format| format as u32),
+        };
+        let surface = Surface::from_dds(dds).map_err(|_| unsupported())?;
+        let (block_dim, block_bytes) = DdsFormat::block_layout(surface.image_format).ok_or_else(unsupported)?;
+
+        let mip_sizes = (0..dds.get_num_mipmap_levels().max(1))
+            .map(|mip| {
+                let w = mip_dimension(width, mip).div_ceil(block_dim) as u64;
+                let h = mip_dimension(height, mip).div_ceil(block_dim) as u64;
+                w * h * mip_dimension(depth, mip) as u64 * block_bytes as u64
+            })
+            .collect();
+        Ok((surface.layers as u64, mip_sizes))
+    }
+
+    /// Length of magic and headers, assuming the header starts at `header_start`.
+    fn header_len(data: &[u8], header_start: usize) -> usize {
+        let fourcc = data.get(header_start + FOURCC_OFFSET..header_start + FOURCC_OFFSET + 4);
+        header_start + HEADER_SIZE + if fourcc == Some(b"DX10") { DX10_HEADER_SIZE } else { 0 }
+    }
+
+    /// Matches the declared mip chain against the stored data and shrinks the header to the
+    /// levels that are really there.
+    fn fit_mip_chain(dds: &mut Dds, report: &mut DdsRepairReport) -> Result<(), DdsRepairError> {
+        let (width, height, depth) = (dds.get_width(), dds.get_height(), dds.get_depth());
+        let (layers, mip_sizes) = Self::mip_sizes(dds)?;
+
+        let declared = mip_sizes.len() as u32;
+        report.declared_mips = declared;
+        report.kept_mips = declared;
+
+        let available = dds.data.len() as u64;
+        // Bytes of one layer holding the mips `first..end`.
+        let layer_bytes = |first: u32, end: u32| mip_sizes[first as usize..end as usize].iter().sum::<u64>();
+
+        let layer_stride = layer_bytes(0, declared);
+        if layers * layer_stride <= available {
+            return Ok(());
+        }
+
+        // Top levels stripped: the data is the tail of the declared chain of every layer,
+        // possibly followed by a little padding.
+        let stripped = (1..declared).find(|&first| {
+            let chain = layers * layer_bytes(first, declared);
+            chain <= available && available - chain < STRIPPED_TAIL_SLACK
+        });
+        if let Some(first) = stripped {
+            report.stripped_top_mips = first;
+            report.kept_mips = declared - first;
+            dds.header.width = mip_dimension(width, first);
+            dds.header.height = mip_dimension(height, first);
+            if dds.header.depth.is_some() {
+                dds.header.depth = Some(mip_dimension(depth, first));
+            }
+            dds.header.mip_map_count = Some(declared - first);
+            dds.header.linear_size = None;
+            dds.header.pitch = None;
+            return Ok(());
+        }
+
+        // Otherwise the data was cut off: keep as many leading mips as every layer still has.
+        let last_layer = (layers - 1) * layer_stride;
+        let kept = (1..declared)
+            .rev()
+            .find(|&count| last_layer + layer_bytes(0, count) <= available)
+            .ok_or(DdsRepairError::NotEnoughData {
+                needed: last_layer + layer_bytes(0, 1),
+                available: dds.data.len(),
+            })?;
+        report.kept_mips = kept;
+        dds.header.mip_map_count = Some(kept);
+
+        // Layers are stored one after another, so later layers move up to close the gaps.
+        if layers > 1 {
+            let (stride, kept_len) = (layer_stride as usize, layer_bytes(0, kept) as usize);
+            dds.data = (0..layers as usize)
+                .flat_map(|layer| &dds.data[layer * stride..layer * stride + kept_len])
+                .copied()
+                .collect();
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Bytes of one layer of `bc1_file`: 128, 32, 8, 8 and 8 for its five mips.
+    const BC1_LAYER_LEN: usize = 184;
+
+    /// 16x16 BC1 texture with its full 5-level mip chain, serialized with a DX10 header.
+    fn bc1_file() -> Vec<u8> {
+        bc1_array_file(1)
+    }
+
+    /// Like `bc1_file` with `layers` array layers; every byte of layer `n` is `0x11 * (n + 1)`.
+    fn bc1_array_file(layers: u8) -> Vec<u8> {
+        let dds = Surface {
+            width: 16,
+            height: 16,
+            depth: 1,
+            layers: layers as u32,
+            mipmaps: 5,
+            image_format: image_dds::ImageFormat::BC1RgbaUnorm,
+            data: (0..layers).flat_map(|layer| vec![0x11 * (layer + 1); BC1_LAYER_LEN]).collect::<Vec<u8>>(),
+        }
+        .to_dds()
+        .unwrap();
+
+        let mut file = Vec::new();
+        dds.write(&mut file).unwrap();
+        file
+    }
+
+    /// 16x16 BC1 texture like `bc1_file`, with a legacy `DXT1` header instead.
+    fn legacy_bc1_file() -> Vec<u8> {
+        let mut dds = Dds::new_d3d(image_dds::ddsfile::NewD3dParams {
+            height: 16,
+            width: 16,
+            depth: None,
+            format: image_dds::ddsfile::D3DFormat::DXT1,
+            mipmap_levels: Some(5),
+            caps2: None,
+        })
+        .unwrap();
+        dds.data = vec![0x33; BC1_LAYER_LEN];
+
+        let mut file = Vec::new();
+        dds.write(&mut file).unwrap();
+        file
+    }
+
+    fn header_len(file: &[u8]) -> usize {
+        DdsRepair::header_len(file, 4)
+    }
+
+    fn decodes(dds: &Dds) -> bool {
+        Surface::from_dds(dds).unwrap().decode_rgba8().is_ok()
+    }
+
+    #[test]
+    fn reads_regular_and_magicless_files() {
+        let file = bc1_file();
+        let (dds, report) = DdsRepair::read(&file).unwrap();
+        assert!(!report.is_modified());
+        assert!(decodes(&dds));
+
+        let (dds, report) = DdsRepair::read(&file[4..]).unwrap();
+        assert!(report.missing_magic);
+        assert!(decodes(&dds));
+    }
+
+    #[test]
+    fn trims_mip_count_to_stored_chain() {
+        let file = bc1_file();
+        let header = header_len(&file);
+        // 16x16 and 8x8 levels: 128 + 32 bytes, plus a few stray bytes.
+        let (dds, report) = DdsRepair::read(&file[..header + 160 + 3]).unwrap();
+        assert_eq!((report.declared_mips, report.kept_mips, report.stripped_top_mips), (5, 2, 0));
+        assert!(decodes(&dds));
+    }
+
+    #[test]
+    fn shrinks_header_when_top_mips_are_stripped() {
+        let file = bc1_file();
+        let header = header_len(&file);
+        let mut stripped = file[..header].to_vec();
+        stripped.extend_from_slice(&file[header + 128..]);
+
+        let (dds, report) = DdsRepair::read(&stripped).unwrap();
+        assert_eq!((report.kept_mips, report.stripped_top_mips), (4, 1));
+        assert_eq!((dds.get_width(), dds.get_height()), (8, 8));
+        assert!(decodes(&dds));
+
+        // Padding after the stripped chain is tolerated, a larger remainder is not.
+        stripped.extend_from_slice(&[0; 3]);
+        let (dds, report) = DdsRepair::read(&stripped).unwrap();
+        assert_eq!((report.kept_mips, report.stripped_top_mips), (4, 1));
+        assert!(decodes(&dds));
+
+        stripped.extend_from_slice(&[0; 16]);
+        assert!(matches!(DdsRepair::read(&stripped), Err(DdsRepairError::NotEnoughData { .. })));
+    }
+
+    #[test]
+    fn keeps_every_layer_of_cut_off_arrays_in_place() {
+        let file = bc1_array_file(2);
+        let header = header_len(&file);
+        // All of layer 0, then the 16x16 and 8x8 levels of layer 1.
+        let (dds, report) = DdsRepair::read(&file[..header + BC1_LAYER_LEN + 160]).unwrap();
+        assert_eq!((report.declared_mips, report.kept_mips), (5, 2));
+
+        assert_eq!(dds.data.len(), 2 * 160);
+        assert!(dds.data[..160].iter().all(|&b| b == 0x11));
+        assert!(dds.data[160..].iter().all(|&b| b == 0x22));
+        assert!(decodes(&dds));
+    }
+
+    #[test]
+    fn rebuilds_headers_cut_in_front_of_the_pixel_data() {
+        let file = legacy_bc1_file();
+        let header = header_len(&file);
+        assert_eq!(header, 4 + HEADER_SIZE);
+        let mut cut = file[..4 + 110].to_vec();
+        cut.extend_from_slice(&file[header..]);
+
+        let (dds, report) = DdsRepair::read(&cut).unwrap();
+        assert!(report.rebuilt_header && !report.missing_magic);
+        assert_eq!((dds.get_width(), dds.get_height(), report.kept_mips), (16, 16, 5));
+        assert_eq!(dds.data, &file[header..]);
+        assert!(decodes(&dds));
+
+        let (dds, report) = DdsRepair::read(&cut[4..]).unwrap();
+        assert!(report.rebuilt_header && report.missing_magic);
+        assert!(decodes(&dds));
+    }
+
+    #[test]
+    fn rebuilds_cut_dx10_headers_from_the_dxgi_format() {
+        let file = bc1_file();
+        let header = header_len(&file);
+        assert_eq!(header, 4 + HEADER_SIZE + DX10_HEADER_SIZE);
+        let mut cut = file[..4 + HEADER_SIZE + 8].to_vec();
+        cut.extend_from_slice(&file[header..]);
+
+        let (dds, report) = DdsRepair::read(&cut).unwrap();
+        assert!(report.rebuilt_header);
+        assert_eq!(dds.get_dxgi_format(), Some(image_dds::ddsfile::DxgiFormat::BC1_UNorm));
+        assert!(decodes(&dds));
+
+        // Without the DXGI format there is nothing to rebuild from.
+        let mut cut = file[..4 + HEADER_SIZE].to_vec();
+        cut.extend_from_slice(&file[header..]);
+        assert!(DdsRepair::read(&cut).is_err());
+    }
+
+    #[test]
+    fn keeps_intact_headers_of_chains_cut_a_few_bytes_short() {
+        let file = bc1_file();
+        let header = header_len(&file);
+        let (dds, report) = DdsRepair::read(&file[..header + BC1_LAYER_LEN - 8]).unwrap();
+
+        assert!(!report.rebuilt_header);
+        assert_eq!((report.declared_mips, report.kept_mips), (5, 4));
+        assert!(decodes(&dds));
+    }
+
+    #[test]
+    fn reports_unrecoverable_data() {
+        let file = bc1_file();
+        let header = header_len(&file);
+
+        assert!(matches!(DdsRepair::read(&file[header..]), Err(DdsRepairError::MissingHeader { .. })));
+        assert!(matches!(DdsRepair::read(&file[..60]), Err(DdsRepairError::TruncatedHeader { .. })));
+        assert!(matches!(
+            DdsRepair::read(&file[..header + 100]),
+            Err(DdsRepairError::NotEnoughData { needed: 128, available: 100 })
+        ));
+    }
+}
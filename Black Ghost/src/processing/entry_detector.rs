@@ -0,0 +1,95 @@
+use std::cmp::Ordering;
+use crate::models::entry_classification::SniffConfidence;
+use crate::models::entry_layout::{DetectionStatus, EntryLayout};
+use crate::models::paz_file::PazFile;
+use crate::processing::bdo_decomp::BdoDecomp;
+use crate::processing::file_sniffer::FileSniffer;
+use crate::processing::paz_entry::PazEntry;
+
+pub struct EntryDetector;
+
+impl EntryDetector {
+    /// Decides how the stored bytes of an entry have to be decoded.
+    ///
+    /// Both the raw and the decrypted interpretation are checked for a container header whose
+    /// lengths agree with the meta sizes. Only if neither validates, content magics decide.
+    pub fn detect(stored: &[u8], file_info: &PazFile) -> EntryLayout {
+        let head_len = stored.len().min(FileSniffer::SNIFF_LEN);
+        let plain_head = &stored[..head_len];
+        let decrypted_head = (stored.len() >= 8).then(|| PazEntry::decrypt_prefix(stored, head_len));
+
+        let plain_container = Self::validate_container(plain_head, stored.len(), file_info);
+        let decrypted_container = decrypted_head
+            .as_deref()
+            .and_then(|head| Self::validate_container(head, stored.len(), file_info));
+
+        match (decrypted_container, plain_container) {
+            (Some(layout), None) => {
+                return EntryLayout { is_encrypted: true, ..layout };
+            }
+            (None, Some(layout)) => return layout,
+            (Some(layout), Some(_)) => {
+                return EntryLayout { status: DetectionStatus::Uncertain, is_encrypted: true, ..layout };
+            }
+            (None, None) => {}
+        }
+
+        let original = file_info.original_size as usize;
+        if stored.len() < original || stored.len() - original >= 8 {
+            return EntryLayout::plain(DetectionStatus::Inconsistent, decrypted_head.is_some());
+        }
+
+        // Less than one ICE block is always stored as is.
+        let Some(decrypted_head) = decrypted_head else {
+            return EntryLayout::plain(DetectionStatus::Certain, false);
+        };
+
+        let content_len = original.min(head_len);
+        let (_, plain_confidence) = FileSniffer::sniff(&plain_head[..content_len]);
+        let (_, decrypted_confidence) = FileSniffer::sniff(&decrypted_head[..content_len]);
+
+        match decrypted_confidence.cmp(&plain_confidence) {
+            Ordering::Greater => EntryLayout::plain(Self::status_for(decrypted_confidence), true),
+            Ordering::Less => EntryLayout::plain(Self::status_for(plain_confidence), false),
+            Ordering::Equal => EntryLayout::plain(DetectionStatus::Uncertain, false),
+        }
+    }
+
+    fn status_for(confidence: SniffConfidence) -> DetectionStatus {
+        if confidence == SniffConfidence::High {
+            DetectionStatus::Certain
+        } else {
+            DetectionStatus::Probable
+        }
+    }
+
+    fn validate_container(head: &[u8], stored_len: usize, file_info: &PazFile) -> Option<EntryLayout> {
+        if head.is_empty() || !PazEntry::is_container_flag(head[0]) {
+            return None;
+        }
+
+        let flags = head[0];
+        let (decomp_len, comp_len, header_size) = BdoDecomp::parse_file_header(head).ok()?;
+
+        if decomp_len != file_info.original_size {
+            return None;
+        }
+        // The container may be followed by up to one block of encryption padding.
+        if comp_len < header_size || comp_len > stored_len || stored_len - comp_len >= 8 {
+            return None;
+        }
+        if (flags & 0x01) == 0 && comp_len != header_size + decomp_len as usize {
+            return None;
+        }
+
+        Some(EntryLayout {
+            status: DetectionStatus::Certain,
+            is_encrypted: false,
+            has_container: true,
+            container_flags: flags,
+            header_size: header_size as u8,
+            header_compressed_size: comp_len as u32,
+            header_original_size: decomp_len,
+        })
+    }
+}
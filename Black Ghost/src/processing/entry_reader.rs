@@ -0,0 +1,305 @@
+use std::io::{self, Read, Seek, SeekFrom};
+use crate::BDO_ICE_KEY;
+use crate::models::entry_layout::EntryLayout;
+use crate::models::paz_file::PazFile;
+use crate::processing::bdo_stream::BdoStreamDecoder;
+use crate::processing::entry_detector::EntryDetector;
+use crate::processing::raw_ice::RawIce;
+
+const STREAM_CHUNK: usize = 64 * 1024;
+/// Output distance between decoder snapshots of a compressed entry. Each snapshot holds up to
+/// two match windows, so a 512 MiB entry keeps about 10 MiB of them.
+const CHECKPOINT_INTERVAL: u64 = 16 * 1024 * 1024;
+
+/// Sequential and random access to the decoded content of a single entry.
+///
+/// Uncompressed entries are decrypted block by block straight from the stored bytes, so reads
+/// only touch the ICE blocks they overlap. Compressed entries are decrypted and decompressed
+/// incrementally while reading forward.
+pub struct EntryReader<'a> {
+    stored: &'a [u8],
+    layout: EntryLayout,
+    ice: Option<Box<RawIce>>,
+    len: u64,
+    pos: u64,
+    scratch: Vec<u8>,
+    stream: Option<ContainerStream>,
+}
+
+/// Decoder state for a compressed entry. It does not borrow the stored bytes, so it can be
+/// taken out of one reader with `into_stream` and handed to the next with `with_stream`.
+///
+/// Seeking resumes from the closest snapshot before the target instead of the start of the entry.
+#[derive(Default)]
+pub struct ContainerStream {
+    decoder: BdoStreamDecoder,
+    fed: usize,
+    output: Vec<u8>,
+    output_start: u64,
+    checkpoints: Vec<Checkpoint>,
+}
+
+struct Checkpoint {
+    decoder: BdoStreamDecoder,
+    fed: usize,
+    position: u64,
+}
+
+impl ContainerStream {
+    fn output_end(&self) -> u64 {
+        self.output_start + self.output.len() as u64
+    }
+
+    /// Moves the decoder to the last snapshot at or before `pos` if that is closer than
+    /// decoding on from the current state.
+    fn seek_to(&mut self, pos: u64) {
+        let checkpoint = self.checkpoints.iter().rev().find(|c| c.position <= pos);
+        let resume_at = checkpoint.map_or(0, |c| c.position);
+        if pos >= self.output_start && resume_at <= self.output_end() {
+            return;
+        }
+
+        match checkpoint {
+            Some(checkpoint) => {
+                self.decoder = checkpoint.decoder.clone();
+                self.fed = checkpoint.fed;
+            }
+            None => {
+                self.decoder = BdoStreamDecoder::new();
+                self.fed = 0;
+            }
+        }
+        self.output.clear();
+        self.output_start = resume_at;
+    }
+
+    fn record_checkpoint(&mut self) {
+        let last = self.checkpoints.last().map_or(0, |c| c.position);
+        let position = self.output_end();
+        if position >= last + CHECKPOINT_INTERVAL && !self.decoder.is_finished() {
+            self.checkpoints.push(Checkpoint { decoder: self.decoder.clone(), fed: self.fed, position });
+        }
+    }
+}
+
+impl<'a> EntryReader<'a> {
+    pub fn new(stored: &'a [u8], file_info: PazFile) -> Self {
+        let layout = EntryDetector::detect(stored, &file_info);
+        Self::with_layout(stored, file_info, layout)
+    }
+
+    /// Like `new`, for callers that already ran `EntryDetector::detect` on the entry.
+    pub fn with_layout(stored: &'a [u8], file_info: PazFile, layout: EntryLayout) -> Self {
+        let ice = layout.is_encrypted.then(|| Box::new(RawIce::new(0, &BDO_ICE_KEY)));
+
+        let len = if layout.has_container {
+            layout.header_original_size as u64
+        } else {
+            stored.len().min(file_info.original_size as usize) as u64
+        };
+
+        Self {
+            stored,
+            layout,
+            ice,
+            len,
+            pos: 0,
+            scratch: Vec::new(),
+            stream: None,
+        }
+    }
+
+    /// Continues from the decoder state of an earlier reader over the same entry.
+    pub fn with_stream(mut self, stream: ContainerStream) -> Self {
+        if self.layout.has_container {
+            self.stream = Some(stream);
+        }
+        self
+    }
+
+    pub fn into_stream(self) -> Option<ContainerStream> {
+        self.stream
+    }
+
+    pub fn layout(&self) -> &EntryLayout {
+        &self.layout
+    }
+
+    pub fn len(&self) -> u64 {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    fn read_container(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let stream = self.stream.get_or_insert_with(ContainerStream::default);
+        stream.seek_to(self.pos);
+
+        loop {
+            let output_end = stream.output_end();
+            if self.pos < output_end {
+                let start = (self.pos - stream.output_start) as usize;
+                let count = buf.len().min(stream.output.len() - start);
+                buf[..count].copy_from_slice(&stream.output[start..start + count]);
+                return Ok(count);
+            }
+            // Reads stop at the announced size, so a finished decoder here produced too little.
+            if stream.decoder.is_finished() || stream.fed >= self.stored.len() {
+                return match stream.decoder.finish_strict() {
+                    Ok(_) => Ok(0),
+                    Err(e) => Err(io::Error::new(io::ErrorKind::InvalidData, e)),
+                };
+            }
+
+            // Output before the read position is no longer needed.
+            stream.output_start = output_end;
+            stream.output.clear();
+
+            let chunk_end = (stream.fed + STREAM_CHUNK).min(self.stored.len());
+            self.scratch.clear();
+            self.scratch.extend_from_slice(&self.stored[stream.fed..chunk_end]);
+
+            if let Some(ice) = &self.ice {
+                let aligned_end = chunk_end.min(self.stored.len() & !7);
+                if aligned_end > stream.fed {
+                    ice.decrypt(&mut self.scratch[..aligned_end - stream.fed]);
+                }
+            }
+            stream.fed = chunk_end;
+
+            stream.decoder
+                .feed(&self.scratch, &mut stream.output)
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+            stream.record_checkpoint();
+        }
+    }
+
+    fn read_stored(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let start = self.pos as usize;
+        let end = start + buf.len().min((self.len - self.pos) as usize);
+
+        let Some(ice) = &self.ice else {
+            buf[..end - start].copy_from_slice(&self.stored[start..end]);
+            return Ok(end - start);
+        };
+
+        // Whole blocks are decrypted; a trailing partial block is stored unencrypted.
+        let block_start = start & !7;
+        let block_end = end.next_multiple_of(8).min(self.stored.len());
+        let aligned_end = block_end.min(self.stored.len() & !7);
+
+        self.scratch.clear();
+        self.scratch.extend_from_slice(&self.stored[block_start..block_end]);
+        if aligned_end > block_start {
+            ice.decrypt(&mut self.scratch[..aligned_end - block_start]);
+        }
+
+        buf[..end - start].copy_from_slice(&self.scratch[start - block_start..end - block_start]);
+        Ok(end - start)
+    }
+}
+
+impl Read for EntryReader<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if buf.is_empty() || self.pos >= self.len {
+            return Ok(0);
+        }
+
+        let count = if self.layout.has_container {
+            self.read_container(buf)?
+        } else {
+            self.read_stored(buf)?
+        };
+
+        self.pos += count as u64;
+        Ok(count)
+    }
+}
+
+impl Seek for EntryReader<'_> {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        let target = match pos {
+            SeekFrom::Start(offset) => Some(offset),
+            SeekFrom::End(offset) => self.len.checked_add_signed(offset),
+            SeekFrom::Current(offset) => self.pos.checked_add_signed(offset),
+        };
+
+        match target {
+            Some(target) => {
+                self.pos = target;
+                Ok(target)
+            }
+            None => Err(io::Error::new(io::ErrorKind::InvalidInput, "seek before start of entry")),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::processing::test_support::{compressed_container, encrypt_entry, padded, paz_file, short_container, Lcg};
+
+    fn read_at(reader: &mut EntryReader<'_>, pos: SeekFrom, len: usize) -> Vec<u8> {
+        reader.seek(pos).unwrap();
+        let mut out = Vec::new();
+        reader.take(len as u64).read_to_end(&mut out).unwrap();
+        out
+    }
+
+    #[test]
+    fn reads_across_the_unencrypted_tail() {
+        let mut rng = Lcg(0x33);
+        let mut content: Vec<u8> = (0..1003).map(|_| rng.next() as u8).collect();
+        content[..4].copy_from_slice(b"DDS ");
+        let stored = encrypt_entry(&content);
+        let mut reader = EntryReader::new(&stored, paz_file(&stored, content.len()));
+        assert!(reader.layout().is_encrypted);
+
+        // Small reads straddle the last whole block and the plain tail.
+        reader.seek(SeekFrom::Start(985)).unwrap();
+        let mut tail = Vec::new();
+        let mut buf = [0u8; 7];
+        loop {
+            let count = reader.read(&mut buf).unwrap();
+            if count == 0 {
+                break;
+            }
+            tail.extend_from_slice(&buf[..count]);
+        }
+        assert_eq!(tail, &content[985..]);
+        assert_eq!(read_at(&mut reader, SeekFrom::End(-10), 100), &content[993..]);
+    }
+
+    #[test]
+    fn seeks_backwards_and_past_the_end_of_compressed_entries() {
+        let (container, content) = compressed_container(&mut Lcg(0x34), 300_000);
+        let stored = encrypt_entry(&padded(container));
+        let mut reader = EntryReader::new(&stored, paz_file(&stored, content.len()));
+        assert_eq!(reader.len(), content.len() as u64);
+
+        assert_eq!(read_at(&mut reader, SeekFrom::Start(250_000), 1000), &content[250_000..251_000]);
+        // Going back restarts the decoder from the beginning of the entry.
+        assert_eq!(read_at(&mut reader, SeekFrom::Start(10), 1000), &content[10..1010]);
+        assert_eq!(read_at(&mut reader, SeekFrom::Current(-500), 20), &content[510..530]);
+        assert_eq!(read_at(&mut reader, SeekFrom::End(-64), 1000), &content[content.len() - 64..]);
+
+        let len = content.len() as u64;
+        assert_eq!(reader.seek(SeekFrom::End(100)).unwrap(), len + 100);
+        assert_eq!(reader.read(&mut [0u8; 16]).unwrap(), 0);
+        assert!(reader.seek(SeekFrom::Current(-(len as i64) - 101)).is_err());
+    }
+
+    #[test]
+    fn fails_when_the_container_ends_short_of_its_size() {
+        let (container, expected) = short_container(&mut Lcg(0x35), 2000);
+        let stored = padded(container);
+        let mut reader = EntryReader::new(&stored, paz_file(&stored, expected.len()));
+
+        let mut out = Vec::new();
+        let err = reader.read_to_end(&mut out).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        assert_eq!(out, &expected[..out.len()]);
+    }
+}
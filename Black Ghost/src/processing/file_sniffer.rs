@@ -0,0 +1,200 @@
+use std::io::Read;
+use crate::models::entry_classification::{EntryClassification, FileKind, SniffConfidence};
+use crate::models::paz_file::PazFile;
+use crate::processing::entry_detector::EntryDetector;
+use crate::processing::entry_reader::EntryReader;
+
+pub struct FileSniffer;
+
+impl FileSniffer {
+    /// Number of leading content bytes the sniffer looks at.
+    pub const SNIFF_LEN: usize = 64;
+
+    /// Sniffs the first `SNIFF_LEN` content bytes. Only the leading ICE blocks are decrypted,
+    /// and compressed entries are decoded just far enough to produce those bytes.
+    pub fn classify(stored: &[u8], file_info: &PazFile) -> EntryClassification {
+        let layout = EntryDetector::detect(stored, file_info);
+
+        let mut head = Vec::with_capacity(Self::SNIFF_LEN);
+        let (kind, confidence) = match EntryReader::with_layout(stored, *file_info, layout)
+            .take(Self::SNIFF_LEN as u64)
+            .read_to_end(&mut head)
+        {
+            Ok(_) => Self::sniff(&head),
+            Err(_) => (FileKind::Unknown, SniffConfidence::None),
+        };
+
+        EntryClassification {
+            kind,
+            confidence,
+            is_encrypted: layout.is_encrypted,
+            is_compressed: layout.is_compressed(),
+        }
+    }
+
+    pub fn sniff(data: &[u8]) -> (FileKind, SniffConfidence) {
+        if data.is_empty() {
+            return (FileKind::Unknown, SniffConfidence::None);
+        }
+
+        if let Some(kind) = Self::sniff_magic(data) {
+            return kind;
+        }
+
+        Self::sniff_text(data)
+    }
+
+    fn sniff_magic(data: &[u8]) -> Option<(FileKind, SniffConfidence)> {
+        let found = match data {
+            [b'D', b'D', b'S', b' ', ..] => (FileKind::Dds, SniffConfidence::High),
+            [0x89, b'P', b'N', b'G', 0x0D, 0x0A, 0x1A, 0x0A, ..] => (FileKind::Png, SniffConfidence::High),
+            [0xFF, 0xD8, 0xFF, ..] => (FileKind::Jpeg, SniffConfidence::High),
+            [0x1B, b'L', b'u', b'a', ..] => (FileKind::LuaBytecode, SniffConfidence::High),
+            [0x1B, b'L', b'J', ..] => (FileKind::LuaJitBytecode, SniffConfidence::High),
+            [b'P', b'A', b'B', b'R', ..] => (FileKind::Pabr, SniffConfidence::High),
+            [b'B', b'K', b'H', b'D', ..] => (FileKind::WwiseBank, SniffConfidence::High),
+            [b'R', b'I', b'F', b'F' | b'X', _, _, _, _, b'W', b'A', b'V', b'E', ..] => {
+                // Wwise Vorbis streams carry the 0xFFFF extensible format tag.
+                let wwise_tag = data.len() >= 22 && data[20] == 0xFF && data[21] == 0xFF;
+                (FileKind::WwiseWem, if wwise_tag { SniffConfidence::High } else { SniffConfidence::Medium })
+            }
+            [0x1A, 0x45, 0xDF, 0xA3, ..] => {
+                let is_webm = memchr::memmem::find(data, b"webm").is_some();
+                (FileKind::WebM, if is_webm { SniffConfidence::High } else { SniffConfidence::Medium })
+            }
+            [b'O', b'g', b'g', b'S', ..] => (FileKind::Ogg, SniffConfidence::High),
+            [b'O', b'T', b'T', b'O', ..] => (FileKind::OpenType, SniffConfidence::High),
+            [b't', b'r', b'u', b'e', ..] | [b't', b't', b'c', b'f', ..] => (FileKind::TrueType, SniffConfidence::High),
+            [0x00, 0x01, 0x00, 0x00, 0x00, tables, ..] if (1..=64).contains(tables) => {
+                (FileKind::TrueType, SniffConfidence::Medium)
+            }
+            [0xFF, 0xFE, b'<', 0x00, ..] => (FileKind::Xml, SniffConfidence::High),
+            [0xFF, 0xFE, ..] | [0xFE, 0xFF, ..] => (FileKind::Utf16Text, SniffConfidence::High),
+            [b'B', b'M', ..] if data.len() >= 14 && data[6..10] == [0, 0, 0, 0] => (FileKind::Bmp, SniffConfidence::Low),
+            _ => return None,
+        };
+
+        Some(found)
+    }
+
+    fn sniff_text(data: &[u8]) -> (FileKind, SniffConfidence) {
+        if Self::looks_like_utf16le(data) {
+            let first = data[0];
+            return if first == b'<' {
+                (FileKind::Xml, SniffConfidence::Medium)
+            } else {
+                (FileKind::Utf16Text, SniffConfidence::Medium)
+            };
+        }
+
+        let text = data.strip_prefix(&[0xEF, 0xBB, 0xBF]).unwrap_or(data);
+        let has_bom = text.len() != data.len();
+
+        let valid_utf8 = match std::str::from_utf8(text) {
+            Ok(_) => true,
+            // A prefix may end in the middle of a multi-byte sequence.
+            Err(e) => e.error_len().is_none(),
+        };
+        if !valid_utf8 || text.iter().any(|&b| b < 0x20 && !matches!(b, b'\t' | b'\n' | b'\r')) {
+            return (FileKind::Unknown, SniffConfidence::None);
+        }
+
+        let trimmed = text.trim_ascii_start();
+        if trimmed.starts_with(b"<?xml") {
+            (FileKind::Xml, SniffConfidence::High)
+        } else if trimmed.starts_with(b"<") {
+            (FileKind::Xml, SniffConfidence::Medium)
+        } else if has_bom {
+            (FileKind::Utf8Text, SniffConfidence::Medium)
+        } else {
+            (FileKind::Utf8Text, SniffConfidence::Low)
+        }
+    }
+
+    fn looks_like_utf16le(data: &[u8]) -> bool {
+        if data.len() < 8 {
+            return false;
+        }
+
+        data.chunks_exact(2).take(16).all(|pair| {
+            pair[1] == 0 && (pair[0] >= 0x20 || matches!(pair[0], b'\t' | b'\n' | b'\r'))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::processing::test_support::{compress_ops, encrypt_entry, padded, paz_file, Op};
+
+    fn utf16le(text: &str) -> Vec<u8> {
+        text.encode_utf16().flat_map(u16::to_le_bytes).collect()
+    }
+
+    #[test]
+    fn sniffs_binary_magics() {
+        let mut wem = b"RIFF\0\0\0\0WAVEfmt \0\0\0\0".to_vec();
+        let plain_wave = wem.clone();
+        wem.extend_from_slice(&[0xFF, 0xFF]);
+
+        let cases: [(&[u8], FileKind, SniffConfidence); 17] = [
+            (b"DDS \x7c\0\0\0", FileKind::Dds, SniffConfidence::High),
+            (b"\x89PNG\r\n\x1a\n\0\0", FileKind::Png, SniffConfidence::High),
+            (b"\xFF\xD8\xFF\xE0", FileKind::Jpeg, SniffConfidence::High),
+            (b"BM\x36\0\0\0\0\0\0\0\x36\0\0\0", FileKind::Bmp, SniffConfidence::Low),
+            (b"\x1bLua\x51\0", FileKind::LuaBytecode, SniffConfidence::High),
+            (b"\x1bLJ\x02", FileKind::LuaJitBytecode, SniffConfidence::High),
+            (b"PABR\x01\0", FileKind::Pabr, SniffConfidence::High),
+            (b"BKHD\x18\0", FileKind::WwiseBank, SniffConfidence::High),
+            (&wem, FileKind::WwiseWem, SniffConfidence::High),
+            (&plain_wave, FileKind::WwiseWem, SniffConfidence::Medium),
+            (b"\x1a\x45\xdf\xa3\x42\x82\x84webm", FileKind::WebM, SniffConfidence::High),
+            (b"\x1a\x45\xdf\xa3\x42\x82\x88matroska", FileKind::WebM, SniffConfidence::Medium),
+            (b"OggS\0\x02", FileKind::Ogg, SniffConfidence::High),
+            (b"OTTO\0\x0b", FileKind::OpenType, SniffConfidence::High),
+            (b"true\0\x0b", FileKind::TrueType, SniffConfidence::High),
+            (b"\0\x01\0\0\0\x0f\0\x80", FileKind::TrueType, SniffConfidence::Medium),
+            (b"\x00\x9c\x13\x07\x01\x02", FileKind::Unknown, SniffConfidence::None),
+        ];
+        for (data, kind, confidence) in cases {
+            assert_eq!(FileSniffer::sniff(data), (kind, confidence), "{data:?}");
+        }
+        assert_eq!(FileSniffer::sniff(&[]), (FileKind::Unknown, SniffConfidence::None));
+    }
+
+    #[test]
+    fn sniffs_text_encodings() {
+        let with_bom = |bom: &[u8], body: &[u8]| [bom, body].concat();
+        let cases = [
+            (b"<?xml version=\"1.0\"?>".to_vec(), FileKind::Xml, SniffConfidence::High),
+            (b"  <root attr=\"1\"/>".to_vec(), FileKind::Xml, SniffConfidence::Medium),
+            (with_bom(&[0xFF, 0xFE], &utf16le("<root/>")), FileKind::Xml, SniffConfidence::High),
+            (utf16le("<root>text</root>"), FileKind::Xml, SniffConfidence::Medium),
+            (with_bom(&[0xFF, 0xFE], &utf16le("plain text")), FileKind::Utf16Text, SniffConfidence::High),
+            (utf16le("plain text here"), FileKind::Utf16Text, SniffConfidence::Medium),
+            (with_bom(&[0xEF, 0xBB, 0xBF], "local x = 1".as_bytes()), FileKind::Utf8Text, SniffConfidence::Medium),
+            ("local x = \"검은사막\"".as_bytes().to_vec(), FileKind::Utf8Text, SniffConfidence::Low),
+            // Cut inside a multi-byte sequence, as a 64-byte prefix can be.
+            ("text 검".as_bytes()[..6].to_vec(), FileKind::Utf8Text, SniffConfidence::Low),
+            (b"text\x01with control bytes".to_vec(), FileKind::Unknown, SniffConfidence::None),
+        ];
+        for (data, kind, confidence) in cases {
+            assert_eq!(FileSniffer::sniff(&data), (kind, confidence), "{data:?}");
+        }
+    }
+
+    #[test]
+    fn classifies_encrypted_compressed_entries_from_their_prefix() {
+        let mut ops: Vec<Op> = b"DDS \x7c\0\0\0".iter().map(|&b| Op::Literal(b)).collect();
+        ops.extend((0..20_000).map(|_| Op::Match { dist: 8, len: 32 }));
+        ops.extend((0..16).map(|_| Op::Literal(b'x')));
+        let (container, expected) = compress_ops(&ops, true);
+
+        let stored = encrypt_entry(&padded(container));
+        let file_info = paz_file(&stored, expected.len());
+
+        let classification = FileSniffer::classify(&stored, &file_info);
+        assert_eq!((classification.kind, classification.confidence), (FileKind::Dds, SniffConfidence::High));
+        assert!(classification.is_encrypted && classification.is_compressed);
+    }
+}
@@ -0,0 +1,205 @@
+use std::io::{self, Read, Write};
+use crate::processing::raw_ice::RawIce;
+
+const STREAM_CHUNK: usize = 64 * 1024;
+
+fn crypt_aligned(ice: &RawIce, data: &mut [u8], encrypt: bool) {
+    match (encrypt, data.len() > 8192) {
+        (true, true) => ice.encrypt_par(data),
+        (true, false) => ice.encrypt(data),
+        (false, true) => ice.decrypt_par(data),
+        (false, false) => ice.decrypt(data),
+    }
+}
+
+/// Decrypts a stream of ICE blocks while reading it.
+///
+/// Every complete 8-byte block is decrypted as soon as it has been read. Fewer than 8 bytes
+/// left at the end of the stream are stored unencrypted by the game and are passed through.
+pub struct IceReader<'a, R> {
+    inner: R,
+    ice: &'a RawIce,
+    buf: Box<[u8]>,
+    // `buf[pos..ready]` is decrypted output, `buf[ready..filled]` a partial block still being read.
+    pos: usize,
+    ready: usize,
+    filled: usize,
+    eof: bool,
+}
+
+impl<'a, R: Read> IceReader<'a, R> {
+    pub fn new(inner: R, ice: &'a RawIce) -> Self {
+        Self {
+            inner,
+            ice,
+            buf: vec![0; STREAM_CHUNK].into_boxed_slice(),
+            pos: 0,
+            ready: 0,
+            filled: 0,
+            eof: false,
+        }
+    }
+
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+
+    fn fill(&mut self) -> io::Result<()> {
+        self.buf.copy_within(self.ready..self.filled, 0);
+        self.filled -= self.ready;
+        self.pos = 0;
+        self.ready = 0;
+
+        while self.ready == 0 && !self.eof {
+            let read = self.inner.read(&mut self.buf[self.filled..])?;
+            self.filled += read;
+
+            if read == 0 {
+                self.eof = true;
+                self.ready = self.filled;
+            } else {
+                let aligned = self.filled & !7;
+                crypt_aligned(self.ice, &mut self.buf[..aligned], false);
+                self.ready = aligned;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<R: Read> Read for IceReader<'_, R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        if self.pos == self.ready {
+            self.fill()?;
+        }
+
+        let count = buf.len().min(self.ready - self.pos);
+        buf[..count].copy_from_slice(&self.buf[self.pos..self.pos + count]);
+        self.pos += count;
+        Ok(count)
+    }
+}
+
+/// Encrypts everything written to it in 8-byte ICE blocks.
+///
+/// A partial block is held back until more data arrives; `finish` writes it unencrypted, as the
+/// game stores it. Dropping the writer finishes it too, but ignores errors.
+pub struct IceWriter<'a, W: Write> {
+    inner: Option<W>,
+    ice: &'a RawIce,
+    buf: Vec<u8>,
+}
+
+impl<'a, W: Write> IceWriter<'a, W> {
+    pub fn new(inner: W, ice: &'a RawIce) -> Self {
+        Self {
+            inner: Some(inner),
+            ice,
+            buf: Vec::with_capacity(STREAM_CHUNK),
+        }
+    }
+
+    /// Writes the trailing partial block unencrypted and returns the inner writer.
+    pub fn finish(mut self) -> io::Result<W> {
+        self.finish_inner()?;
+        Ok(self.inner.take().unwrap())
+    }
+
+    fn finish_inner(&mut self) -> io::Result<()> {
+        self.write_aligned()?;
+        let inner = self.inner.as_mut().unwrap();
+        inner.write_all(&self.buf)?;
+        self.buf.clear();
+        inner.flush()
+    }
+
+    fn write_aligned(&mut self) -> io::Result<()> {
+        let aligned = self.buf.len() & !7;
+        if aligned == 0 {
+            return Ok(());
+        }
+
+        crypt_aligned(self.ice, &mut self.buf[..aligned], true);
+        self.inner.as_mut().unwrap().write_all(&self.buf[..aligned])?;
+        self.buf.drain(..aligned);
+        Ok(())
+    }
+}
+
+impl<W: Write> Write for IceWriter<'_, W> {
+    fn write(&mut self, data: &[u8]) -> io::Result<usize> {
+        let count = data.len().min(STREAM_CHUNK - self.buf.len());
+        self.buf.extend_from_slice(&data[..count]);
+        if self.buf.len() == STREAM_CHUNK {
+            self.write_aligned()?;
+        }
+        Ok(count)
+    }
+
+    /// Flushes every complete block. A partial block stays buffered until `finish`.
+    fn flush(&mut self) -> io::Result<()> {
+        self.write_aligned()?;
+        self.inner.as_mut().unwrap().flush()
+    }
+}
+
+impl<W: Write> Drop for IceWriter<'_, W> {
+    fn drop(&mut self) {
+        if self.inner.is_some() {
+            let _ = self.finish_inner();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::BDO_ICE_KEY;
+    use crate::processing::test_support::Lcg;
+
+    /// Hands out at most `step` bytes per call to exercise partial reads.
+    struct Trickle<'a> {
+        data: &'a [u8],
+        step: usize,
+    }
+
+    impl Read for Trickle<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            let count = buf.len().min(self.step).min(self.data.len());
+            buf[..count].copy_from_slice(&self.data[..count]);
+            self.data = &self.data[count..];
+            Ok(count)
+        }
+    }
+
+    #[test]
+    fn streams_match_in_memory_cipher_with_plain_tail() {
+        let ice = RawIce::new(0, &BDO_ICE_KEY);
+        let mut rng = Lcg(0x57EA);
+
+        for len in [0, 5, 8, 13, 64, 1001, STREAM_CHUNK + 3, 3 * STREAM_CHUNK + 7] {
+            let plain: Vec<u8> = (0..len).map(|_| rng.next() as u8).collect();
+            let mut expected = plain.clone();
+            let aligned = len & !7;
+            ice.encrypt(&mut expected[..aligned]);
+
+            for step in [1, 7, 4096, usize::MAX] {
+                let mut writer = IceWriter::new(Vec::new(), &ice);
+                for chunk in plain.chunks(step.min(len.max(1))) {
+                    writer.write_all(chunk).unwrap();
+                }
+                let encrypted = writer.finish().unwrap();
+                assert_eq!(encrypted, expected, "write len {len}, step {step}");
+
+                let mut decrypted = Vec::new();
+                IceReader::new(Trickle { data: &encrypted, step }, &ice)
+                    .read_to_end(&mut decrypted)
+                    .unwrap();
+                assert_eq!(decrypted, plain, "read len {len}, step {step}");
+            }
+        }
+    }
+}
@@ -0,0 +1,229 @@
+use std::fmt::{self, Write as _};
+use std::io::{self, Read};
+use flate2::read::ZlibDecoder;
+use crate::util::text_escape::{json_string, tsv_field};
+
+/// Length, type, id1, id2 and id3 in front of every string.
+const RECORD_HEADER_LEN: usize = 16;
+/// Two UTF-16 NULs after every string.
+const TERMINATOR_LEN: usize = 4;
+
+#[derive(Debug)]
+pub enum LocError {
+    /// Shorter than the 4-byte size prefix.
+    TooShort { len: usize },
+    Inflate(io::Error),
+    SizeMismatch { declared: u32, actual: usize },
+    /// A record runs past the end of the table.
+    Truncated { offset: usize },
+}
+
+impl fmt::Display for LocError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::TooShort { len } => write!(f, "localization file too short: {len} bytes"),
+            Self::Inflate(e) => write!(f, "cannot decompress localization file: {e}"),
+            Self::SizeMismatch { declared, actual } => {
+                write!(f, "decompressed to {actual} bytes, header declares {declared}")
+            }
+            Self::Truncated { offset } => write!(f, "string record at offset {offset} is truncated"),
+        }
+    }
+}
+
+impl std::error::Error for LocError {}
+
+impl From<io::Error> for LocError {
+    fn from(e: io::Error) -> Self {
+        Self::Inflate(e)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LocRecord {
+    pub string_type: u32,
+    pub id1: u32,
+    pub id2: u16,
+    /// The two trailing id bytes, read as one little-endian value.
+    pub id3: u16,
+    pub text: String,
+}
+
+/// String table of a `languagedata_*.loc` file.
+///
+/// The file is a little-endian `u32` with the decompressed size followed by a zlib stream.
+/// Decompressed, it is a sequence of records: `u32` text length in UTF-16 units, `u32` type,
+/// `u32` id1, `u16` id2, `u16` id3, the UTF-16LE text and a 4-byte NUL terminator.
+pub struct LocTable {
+    pub records: Vec<LocRecord>,
+}
+
+impl LocTable {
+    pub fn decode(data: &[u8]) -> Result<Self, LocError> {
+        if data.len() < 4 {
+            return Err(LocError::TooShort { len: data.len() });
+        }
+        let declared = u32::from_le_bytes(data[..4].try_into().unwrap());
+
+        // The declared size only sizes the buffer, so a corrupt prefix cannot force a huge allocation.
+        // Inflating stops one byte past it, which is enough to tell that the stream is longer.
+        let mut plain = Vec::with_capacity((declared as usize).min(data.len() * 16));
+        ZlibDecoder::new(&data[4..]).take(declared as u64 + 1).read_to_end(&mut plain)?;
+        if plain.len() != declared as usize {
+            return Err(LocError::SizeMismatch { declared, actual: plain.len() });
+        }
+        Self::parse(&plain)
+    }
+
+    /// Parses the decompressed record stream.
+    pub fn parse(plain: &[u8]) -> Result<Self, LocError> {
+        let mut records = Vec::new();
+        let mut pos = 0;
+
+        while pos < plain.len() {
+            let rest = &plain[pos..];
+            // Some tables end in zero padding rather than another record.
+            if rest.iter().all(|&b| b == 0) {
+                break;
+            }
+            if rest.len() < RECORD_HEADER_LEN {
+                return Err(LocError::Truncated { offset: pos });
+            }
+
+            let u32_at = |at: usize| u32::from_le_bytes(rest[at..at + 4].try_into().unwrap());
+            let u16_at = |at: usize| u16::from_le_bytes(rest[at..at + 2].try_into().unwrap());
+            let text_len = u32_at(0) as usize * 2;
+            let record_len = RECORD_HEADER_LEN + text_len + TERMINATOR_LEN;
+            if rest.len() < record_len {
+                return Err(LocError::Truncated { offset: pos });
+            }
+
+            let units: Vec<u16> = rest[RECORD_HEADER_LEN..RECORD_HEADER_LEN + text_len]
+                .chunks_exact(2)
+                .map(|unit| u16::from_le_bytes([unit[0], unit[1]]))
+                .collect();
+            records.push(LocRecord {
+                string_type: u32_at(4),
+                id1: u32_at(8),
+                id2: u16_at(12),
+                id3: u16_at(14),
+                text: String::from_utf16_lossy(&units),
+            });
+            pos += record_len;
+        }
+
+        Ok(Self { records })
+    }
+
+    /// One `type, id1, id2, id3, text` line per record. Tabs, line breaks and backslashes in
+    /// the text are written as `\t`, `\n`, `\r` and `\\`.
+    pub fn to_tsv(&self) -> String {
+        let mut out = String::from("type\tid1\tid2\tid3\ttext\n");
+        for record in &self.records {
+            let _ = writeln!(
+                out,
+                "{}\t{}\t{}\t{}\t{}",
+                record.string_type,
+                record.id1,
+                record.id2,
+                record.id3,
+                tsv_field(&record.text)
+            );
+        }
+        out
+    }
+
+    pub fn to_json(&self) -> String {
+        let mut out = String::from("[\n");
+        for (i, record) in self.records.iter().enumerate() {
+            let _ = write!(
+                out,
+                "  {{\"type\": {}, \"id1\": {}, \"id2\": {}, \"id3\": {}, \"text\": {}}}",
+                record.string_type,
+                record.id1,
+                record.id2,
+                record.id3,
+                json_string(&record.text)
+            );
+            out.push_str(if i + 1 < self.records.len() { ",\n" } else { "\n" });
+        }
+        out.push_str("]\n");
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+    use flate2::write::ZlibEncoder;
+    use flate2::Compression;
+    use super::*;
+
+    fn record(out: &mut Vec<u8>, string_type: u32, id1: u32, id2: u16, id3: u16, text: &str) {
+        let units: Vec<u16> = text.encode_utf16().collect();
+        out.extend_from_slice(&(units.len() as u32).to_le_bytes());
+        out.extend_from_slice(&string_type.to_le_bytes());
+        out.extend_from_slice(&id1.to_le_bytes());
+        out.extend_from_slice(&id2.to_le_bytes());
+        out.extend_from_slice(&id3.to_le_bytes());
+        units.iter().for_each(|unit| out.extend_from_slice(&unit.to_le_bytes()));
+        out.extend_from_slice(&[0; TERMINATOR_LEN]);
+    }
+
+    fn loc_file(plain: &[u8]) -> Vec<u8> {
+        let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(plain).unwrap();
+        let mut file = (plain.len() as u32).to_le_bytes().to_vec();
+        file.extend(encoder.finish().unwrap());
+        file
+    }
+
+    #[test]
+    fn decodes_records_and_exports_them() {
+        let mut plain = Vec::new();
+        record(&mut plain, 0, 1001, 0, 0, "Velia");
+        record(&mut plain, 9, 70_000, 3, 0x0201, "검은사막\tline\nbreak");
+        record(&mut plain, 1, 2, 0, 0, "");
+
+        let table = LocTable::decode(&loc_file(&plain)).unwrap();
+        assert_eq!(table.records.len(), 3);
+        assert_eq!(
+            table.records[1],
+            LocRecord {
+                string_type: 9,
+                id1: 70_000,
+                id2: 3,
+                id3: 0x0201,
+                text: "검은사막\tline\nbreak".to_owned(),
+            }
+        );
+
+        let tsv = table.to_tsv();
+        assert_eq!(
+            tsv,
+            "type\tid1\tid2\tid3\ttext\n0\t1001\t0\t0\tVelia\n9\t70000\t3\t513\t검은사막\\tline\\nbreak\n1\t2\t0\t0\t\n"
+        );
+        assert!(table.to_json().contains(r#"{"type": 9, "id1": 70000, "id2": 3, "id3": 513, "text": "검은사막\tline\nbreak"},"#));
+    }
+
+    #[test]
+    fn rejects_damaged_files() {
+        let mut plain = Vec::new();
+        record(&mut plain, 0, 1, 0, 0, "text");
+
+        let mut file = loc_file(&plain);
+        file[0] ^= 1;
+        assert!(matches!(LocTable::decode(&file), Err(LocError::SizeMismatch { .. })));
+
+        // A stream far longer than declared is cut off right after the declared size.
+        let mut file = loc_file(&vec![0; 1 << 20]);
+        file[..4].copy_from_slice(&16u32.to_le_bytes());
+        assert!(matches!(LocTable::decode(&file), Err(LocError::SizeMismatch { declared: 16, actual: 17 })));
+        assert!(matches!(LocTable::decode(&[1, 0]), Err(LocError::TooShort { len: 2 })));
+        assert!(matches!(
+            LocTable::parse(&plain[..plain.len() - 6]),
+            Err(LocError::Truncated { offset: 0 })
+        ));
+        assert!(LocTable::parse(&[plain.as_slice(), &[0; 8]].concat()).is_ok());
+    }
+}
@@ -0,0 +1,388 @@
+use std::fmt::{self, Write as _};
+use std::fs;
+use std::io;
+use std::panic::{self, AssertUnwindSafe};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU8, AtomicUsize, Ordering};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+use luadec::LuaDecompiler;
+use rayon::prelude::*;
+use crate::models::bdo_index::BdoIndex;
+use crate::models::lua_bulk_summary::LuaBulkSummary;
+use crate::models::paz_archive::PazArchive;
+use crate::models::paz_file::PazFile;
+use crate::processing::lua_chunk::{LuaChunk, LuaChunkError};
+use crate::processing::lua_disassembler::LuaDisassembler;
+
+/// Appended to the bytecode path of failed entries, e.g. `quest.luac.dis`.
+const DISASSEMBLY_EXTENSION: &str = "dis";
+pub const REPORT_FILE_NAME: &str = "lua_decompile_report.txt";
+/// Once this many timed-out decompiler threads are still running, no more are started and the
+/// remaining files are reported as timed out.
+pub const MAX_ABANDONED_WORKERS: usize = 8;
+
+/// Decompiler threads that ran past their timeout and have not finished yet, across all runs.
+static ABANDONED_WORKERS: AtomicUsize = AtomicUsize::new(0);
+
+const WORKER_RUNNING: u8 = 0;
+const WORKER_DONE: u8 = 1;
+const WORKER_ABANDONED: u8 = 2;
+
+#[derive(Debug)]
+pub enum LuaBulkFailure {
+    Unreadable,
+    /// Not Lua 5.1 bytecode; there is nothing to decompile or disassemble.
+    Unsupported(LuaChunkError),
+    Decompile(String),
+    Timeout(Duration),
+    Panic(String),
+    Write(io::Error),
+}
+
+impl LuaBulkFailure {
+    /// Short cause used to group failures in the report.
+    pub fn cause(&self) -> &'static str {
+        match self {
+            Self::Unreadable => "unreadable",
+            Self::Unsupported(_) => "unsupported",
+            Self::Decompile(_) => "decompile error",
+            Self::Timeout(_) => "timeout",
+            Self::Panic(_) => "panic",
+            Self::Write(_) => "write error",
+        }
+    }
+}
+
+impl fmt::Display for LuaBulkFailure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Unreadable => write!(f, "entry could not be read or decrypted"),
+            Self::Unsupported(e) => write!(f, "{e}"),
+            Self::Decompile(message) => write!(f, "{message}"),
+            Self::Timeout(timeout) => write!(f, "decompiler did not finish within {} ms", timeout.as_millis()),
+            Self::Panic(message) => write!(f, "decompiler panicked: {message}"),
+            Self::Write(e) => write!(f, "cannot write output: {e}"),
+        }
+    }
+}
+
+impl std::error::Error for LuaBulkFailure {}
+
+pub struct LuaBulkEntry {
+    pub path: String,
+    pub result: Result<(), LuaBulkFailure>,
+}
+
+pub struct LuaBulkReport {
+    pub entries: Vec<LuaBulkEntry>,
+}
+
+impl LuaBulkReport {
+    pub fn summary(&self) -> LuaBulkSummary {
+        let mut summary = LuaBulkSummary { total: self.entries.len() as u32, ..Default::default() };
+        for entry in &self.entries {
+            let counter = match &entry.result {
+                Ok(()) => &mut summary.decompiled,
+                Err(LuaBulkFailure::Unreadable) => &mut summary.unreadable,
+                Err(LuaBulkFailure::Unsupported(_)) => &mut summary.unsupported,
+                Err(LuaBulkFailure::Decompile(_)) => &mut summary.decompile_errors,
+                Err(LuaBulkFailure::Timeout(_)) => &mut summary.timeouts,
+                Err(LuaBulkFailure::Panic(_)) => &mut summary.panics,
+                Err(LuaBulkFailure::Write(_)) => &mut summary.write_errors,
+            };
+            *counter += 1;
+        }
+        summary
+    }
+
+    /// Totals per cause, followed by one tab-separated `path, cause, message` line per failure.
+    pub fn to_text(&self) -> String {
+        let summary = self.summary();
+        let mut out = String::new();
+        let _ = writeln!(out, "entries: {}", summary.total);
+        let _ = writeln!(out, "decompiled: {}", summary.decompiled);
+        let _ = writeln!(out, "failed: {}", summary.total - summary.decompiled);
+        for (cause, count) in [
+            ("decompile error", summary.decompile_errors),
+            ("timeout", summary.timeouts),
+            ("panic", summary.panics),
+            ("unsupported", summary.unsupported),
+            ("unreadable", summary.unreadable),
+            ("write error", summary.write_errors),
+        ] {
+            let _ = writeln!(out, "  {cause}: {count}");
+        }
+
+        out.push_str("\npath\tcause\tmessage\n");
+        for entry in &self.entries {
+            if let Err(failure) = &entry.result {
+                let message = failure.to_string().replace(['\t', '\n', '\r'], " ");
+                let _ = writeln!(out, "{}\t{}\t{}", entry.path, failure.cause(), message);
+            }
+        }
+        out
+    }
+}
+
+/// Decompiles every `.luac` entry into a folder tree in parallel.
+///
+/// Each file is decompiled on its own thread so a decompiler that hangs or panics only costs
+/// that file. A thread that runs past the timeout cannot be stopped; it is abandoned and
+/// finishes in the background. At most `MAX_ABANDONED_WORKERS` of them are left running.
+pub struct LuaBulkDecompiler<'a> {
+    archive: &'a PazArchive,
+    timeout: Duration,
+}
+
+impl<'a> LuaBulkDecompiler<'a> {
+    pub fn new(archive: &'a PazArchive, timeout: Duration) -> Self {
+        Self { archive, timeout }
+    }
+
+    /// Writes `.lua` files for entries that decompile. Failed entries keep their `.luac`
+    /// bytecode next to a `.luac.dis` listing. `progress` gets the number of finished entries
+    /// and the total.
+    pub fn run(&self, index: &BdoIndex, output_dir: &Path, progress: impl Fn(usize, usize) + Sync) -> LuaBulkReport {
+        let files: Vec<&PazFile> = index
+            .paz_files
+            .iter()
+            .filter(|file_info| {
+                index.metadata.file_names[file_info.file_id as usize]
+                    .to_ascii_lowercase()
+                    .ends_with(".luac")
+            })
+            .collect();
+
+        let finished = AtomicUsize::new(0);
+        let mut entries: Vec<LuaBulkEntry> = files
+            .par_iter()
+            .map(|file_info| {
+                let path = index.entry_path(file_info);
+                let result = self.process(file_info, &output_dir.join(&path));
+                progress(finished.fetch_add(1, Ordering::Relaxed) + 1, files.len());
+                LuaBulkEntry { path, result }
+            })
+            .collect();
+
+        entries.sort_by(|a, b| a.path.cmp(&b.path));
+        LuaBulkReport { entries }
+    }
+
+    fn process(&self, file_info: &PazFile, bytecode_path: &Path) -> Result<(), LuaBulkFailure> {
+        let data: Arc<[u8]> = self.archive.read(file_info).map_err(|_| LuaBulkFailure::Unreadable)?.into();
+        if let Some(parent) = bytecode_path.parent() {
+            fs::create_dir_all(parent).map_err(LuaBulkFailure::Write)?;
+        }
+
+        if let Err(e) = LuaChunk::parse_header(&data) {
+            fs::write(bytecode_path, &data).map_err(LuaBulkFailure::Write)?;
+            return Err(LuaBulkFailure::Unsupported(e));
+        }
+
+        match Self::decompile(Arc::clone(&data), self.timeout, &ABANDONED_WORKERS) {
+            Ok(code) => fs::write(bytecode_path.with_extension("lua"), code).map_err(LuaBulkFailure::Write),
+            Err(failure) => {
+                let listing = LuaDisassembler::fallback_listing(&failure.to_string(), &data);
+                fs::write(bytecode_path, &data).map_err(LuaBulkFailure::Write)?;
+                fs::write(Self::disassembly_path(bytecode_path), listing).map_err(LuaBulkFailure::Write)?;
+                Err(failure)
+            }
+        }
+    }
+
+    /// Path of the listing written next to bytecode that failed to decompile.
+    pub fn disassembly_path(bytecode_path: &Path) -> PathBuf {
+        let mut path = bytecode_path.as_os_str().to_owned();
+        path.push(".");
+        path.push(DISASSEMBLY_EXTENSION);
+        PathBuf::from(path)
+    }
+
+    /// Runs `luadec` on a worker thread and waits at most `timeout` for it. `abandoned` counts
+    /// the workers still running past their timeout; at the limit, no thread is started.
+    fn decompile(
+        data: Arc<[u8]>,
+        timeout: Duration,
+        abandoned: &'static AtomicUsize,
+    ) -> Result<String, LuaBulkFailure> {
+        if abandoned.load(Ordering::Acquire) >= MAX_ABANDONED_WORKERS {
+            return Err(LuaBulkFailure::Timeout(timeout));
+        }
+
+        let state = Arc::new(AtomicU8::new(WORKER_RUNNING));
+        let worker_state = Arc::clone(&state);
+        let (sender, receiver) = mpsc::channel();
+        thread::Builder::new()
+            .name("lua-decompile".to_owned())
+            .spawn(move || {
+                let result = panic::catch_unwind(AssertUnwindSafe(|| LuaDecompiler::new().decompile(&data)));
+                let _ = sender.send(result);
+                let finished =
+                    worker_state.compare_exchange(WORKER_RUNNING, WORKER_DONE, Ordering::AcqRel, Ordering::Acquire);
+                if finished.is_err() {
+                    abandoned.fetch_sub(1, Ordering::AcqRel);
+                }
+            })
+            .map_err(|e| LuaBulkFailure::Decompile(format!("cannot start decompiler thread: {e}")))?;
+
+        match receiver.recv_timeout(timeout) {
+            Ok(Ok(Ok(code))) => Ok(code),
+            Ok(Ok(Err(e))) => Err(LuaBulkFailure::Decompile(e.to_string())),
+            Ok(Err(payload)) => Err(LuaBulkFailure::Panic(Self::panic_message(payload.as_ref()))),
+            Err(RecvTimeoutError::Timeout) => {
+                // Counted before the state changes, so the worker can never decrement first.
+                abandoned.fetch_add(1, Ordering::AcqRel);
+                let marked =
+                    state.compare_exchange(WORKER_RUNNING, WORKER_ABANDONED, Ordering::AcqRel, Ordering::Acquire);
+                if marked.is_err() {
+                    // The worker finished in the meantime.
+                    abandoned.fetch_sub(1, Ordering::AcqRel);
+                }
+                Err(LuaBulkFailure::Timeout(timeout))
+            }
+            Err(RecvTimeoutError::Disconnected) => {
+                Err(LuaBulkFailure::Panic("decompiler thread exited without a result".to_owned()))
+            }
+        }
+    }
+
+    fn panic_message(payload: &(dyn std::any::Any + Send)) -> String {
+        if let Some(message) = payload.downcast_ref::<&str>() {
+            (*message).to_owned()
+        } else if let Some(message) = payload.downcast_ref::<String>() {
+            message.clone()
+        } else {
+            "unknown panic".to_owned()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::pad_meta::PadMeta;
+    use crate::processing::test_support::{encrypt_entry, lua_chunk, paz_folder};
+    use crate::util::folder_name_tuple::FolderNameTuple;
+
+    static NO_ABANDONED_WORKERS: AtomicUsize = AtomicUsize::new(0);
+
+    /// `lua_chunk` with its main function cut off after the header and source name.
+    fn broken_chunk() -> Vec<u8> {
+        let mut chunk = lua_chunk(false)[..30].to_vec();
+        chunk.extend_from_slice(&[0xFF; 10]);
+        chunk
+    }
+
+    fn index(names: &[&str], paz_files: Vec<PazFile>) -> BdoIndex {
+        BdoIndex {
+            metadata: PadMeta {
+                version: 0,
+                paz_file_count: 1,
+                file_names: names.iter().map(|&name| name.to_owned().into()).collect::<Vec<_>>().into(),
+                folder_paths: vec![FolderNameTuple {
+                    folder_name: "gamecommondata/".to_owned().into(),
+                    folder_index: 0,
+                }]
+                .into(),
+            },
+            paz_files: paz_files.into(),
+        }
+    }
+
+    #[test]
+    fn worker_reports_errors_instead_of_failing() {
+        let decompile = |data: &[u8]| {
+            LuaBulkDecompiler::decompile(Arc::from(data), Duration::from_secs(10), &NO_ABANDONED_WORKERS)
+        };
+
+        assert!(matches!(decompile(b"not bytecode"), Err(LuaBulkFailure::Decompile(_))));
+        assert!(matches!(decompile(&broken_chunk()), Err(LuaBulkFailure::Decompile(_))));
+        let code = decompile(&lua_chunk(false)).unwrap();
+        assert!(code.contains("print"), "{code}");
+        assert_eq!(NO_ABANDONED_WORKERS.load(Ordering::Acquire), 0);
+    }
+
+    #[test]
+    fn stops_starting_workers_once_too_many_are_stuck() {
+        static STUCK_WORKERS: AtomicUsize = AtomicUsize::new(MAX_ABANDONED_WORKERS);
+
+        let result = LuaBulkDecompiler::decompile(Arc::from(lua_chunk(false)), Duration::from_secs(10), &STUCK_WORKERS);
+        assert!(matches!(result, Err(LuaBulkFailure::Timeout(_))));
+        assert_eq!(STUCK_WORKERS.load(Ordering::Acquire), MAX_ABANDONED_WORKERS);
+    }
+
+    #[test]
+    fn run_writes_sources_listings_and_unsupported_bytecode() {
+        let ok = lua_chunk(false);
+        let broken = broken_chunk();
+        let jit = b"\x1bLJ\x02 luajit bytecode".to_vec();
+        let text = b"not lua".to_vec();
+        let (dir, files) = paz_folder(
+            "lua_bulk",
+            &[
+                (encrypt_entry(&ok), ok.len()),
+                (encrypt_entry(&broken), broken.len()),
+                (jit.clone(), jit.len()),
+                (text.clone(), text.len()),
+            ],
+        );
+        let index = index(&["ok.luac", "broken.LUAC", "jit.luac", "readme.txt"], files);
+        let archive = PazArchive::open(dir.to_str().unwrap());
+        let output = dir.join("out");
+
+        let finished = AtomicUsize::new(0);
+        let report = LuaBulkDecompiler::new(&archive, Duration::from_secs(10)).run(&index, &output, |_, total| {
+            assert_eq!(total, 3);
+            finished.fetch_add(1, Ordering::Relaxed);
+        });
+        assert_eq!(finished.load(Ordering::Relaxed), 3);
+
+        let summary = report.summary();
+        assert_eq!((summary.total, summary.decompiled, summary.decompile_errors, summary.unsupported), (3, 1, 1, 1));
+        let paths: Vec<_> = report.entries.iter().map(|entry| entry.path.as_str()).collect();
+        assert_eq!(paths, ["gamecommondata/broken.LUAC", "gamecommondata/jit.luac", "gamecommondata/ok.luac"]);
+
+        let folder = output.join("gamecommondata");
+        let source = fs::read_to_string(folder.join("ok.lua")).unwrap();
+        assert!(source.contains("print"), "{source}");
+        assert!(!folder.join("ok.luac").exists());
+
+        assert_eq!(fs::read(folder.join("broken.LUAC")).unwrap(), broken);
+        let listing = fs::read_to_string(folder.join("broken.LUAC.dis")).unwrap();
+        assert!(listing.starts_with("-- Decompile Error: "), "{listing}");
+
+        assert_eq!(fs::read(folder.join("jit.luac")).unwrap(), jit);
+        assert!(!folder.join("jit.luac.dis").exists());
+        assert!(!folder.join("readme.txt").exists());
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn report_counts_and_lists_failures() {
+        let entry = |path: &str, result| LuaBulkEntry { path: path.to_owned(), result };
+        let report = LuaBulkReport {
+            entries: vec![
+                entry("a/ok.luac", Ok(())),
+                entry("a/slow.luac", Err(LuaBulkFailure::Timeout(Duration::from_millis(250)))),
+                entry("b/jit.luac", Err(LuaBulkFailure::Unsupported(LuaChunkError::LuaJit { version: 2 }))),
+                entry("b/bad.luac", Err(LuaBulkFailure::Decompile("bad\tjump\ntarget".to_owned()))),
+            ],
+        };
+
+        let summary = report.summary();
+        assert_eq!((summary.total, summary.decompiled, summary.timeouts, summary.unsupported), (4, 1, 1, 1));
+        assert_eq!(summary.decompile_errors, 1);
+
+        let text = report.to_text();
+        assert!(text.starts_with("entries: 4\ndecompiled: 1\nfailed: 3\n"));
+        assert!(text.contains("a/slow.luac\ttimeout\tdecompiler did not finish within 250 ms\n"));
+        assert!(text.contains("b/bad.luac\tdecompile error\tbad jump target\n"));
+        assert!(!text.contains("a/ok.luac"));
+
+        let path = LuaBulkDecompiler::disassembly_path(Path::new("out/a/bad.luac"));
+        assert_eq!(path, Path::new("out/a/bad.luac.dis"));
+    }
+}
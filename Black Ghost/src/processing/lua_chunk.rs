@@ -0,0 +1,370 @@
+use std::fmt;
+
+const SIGNATURE: &[u8; 4] = b"\x1bLua";
+const LUAJIT_SIGNATURE: &[u8; 3] = b"\x1bLJ";
+const VERSION_51: u8 = 0x51;
+const HEADER_LEN: usize = 12;
+
+/// Nested functions deeper than this are treated as corrupt data rather than recursed into.
+const MAX_DEPTH: usize = 200;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LuaChunkError {
+    /// The data does not start with the `\x1bLua` signature.
+    NotLuaChunk,
+    /// LuaJIT bytecode, which `luadec` and the disassembler cannot read.
+    LuaJit { version: u8 },
+    UnsupportedVersion { version: u8 },
+    UnsupportedFormat { format: u8 },
+    /// Type sizes this parser cannot read, e.g. a 2-byte `int`.
+    UnsupportedSizes {
+        int_size: u8,
+        size_t_size: u8,
+        instruction_size: u8,
+        number_size: u8,
+    },
+    /// The chunk ends in the middle of a field or declares more items than it holds.
+    Truncated { offset: usize, needed: usize },
+    InvalidConstant { offset: usize, tag: u8 },
+    TooDeep,
+}
+
+impl fmt::Display for LuaChunkError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NotLuaChunk => write!(f, "not a Lua bytecode chunk"),
+            Self::LuaJit { version } => {
+                write!(f, "LuaJIT bytecode (dump version {version}) is not supported, only Lua 5.1")
+            }
+            Self::UnsupportedVersion { version } => {
+                write!(f, "Lua version {}.{} is not supported, only 5.1", version >> 4, version & 0xF)
+            }
+            Self::UnsupportedFormat { format } => write!(f, "unsupported bytecode format {format}, expected 0"),
+            Self::UnsupportedSizes { int_size, size_t_size, instruction_size, number_size } => write!(
+                f,
+                "unsupported type sizes: int {int_size}, size_t {size_t_size}, instruction {instruction_size}, number {number_size}"
+            ),
+            Self::Truncated { offset, needed } => write!(f, "chunk truncated: {needed} bytes needed at offset {offset}"),
+            Self::InvalidConstant { offset, tag } => write!(f, "invalid constant type {tag} at offset {offset}"),
+            Self::TooDeep => write!(f, "functions nested more than {MAX_DEPTH} levels deep"),
+        }
+    }
+}
+
+impl std::error::Error for LuaChunkError {}
+
+/// The 12-byte header in front of every Lua 5.1 chunk.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LuaHeader {
+    pub version: u8,
+    pub format: u8,
+    pub little_endian: bool,
+    pub int_size: u8,
+    pub size_t_size: u8,
+    pub instruction_size: u8,
+    pub number_size: u8,
+    /// Numbers are stored as integers instead of floating point.
+    pub integral: bool,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum LuaConstant {
+    Nil,
+    Boolean(bool),
+    Number(f64),
+    Integer(i64),
+    String(Vec<u8>),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LuaLocal {
+    pub name: Vec<u8>,
+    pub start_pc: u32,
+    pub end_pc: u32,
+}
+
+/// One function prototype with its nested prototypes.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LuaFunction {
+    /// Chunk name such as `@script.lua`; `None` when inherited from the parent or stripped.
+    pub source: Option<Vec<u8>>,
+    pub line_defined: u32,
+    pub last_line_defined: u32,
+    pub upvalue_count: u8,
+    pub param_count: u8,
+    pub vararg_flags: u8,
+    pub max_stack_size: u8,
+    pub code: Vec<u32>,
+    pub constants: Vec<LuaConstant>,
+    pub functions: Vec<LuaFunction>,
+    /// Source line of each instruction; empty when debug info was stripped.
+    pub line_info: Vec<u32>,
+    pub locals: Vec<LuaLocal>,
+    pub upvalue_names: Vec<Vec<u8>>,
+}
+
+/// A parsed Lua 5.1 bytecode chunk, as written by `luac` or `string.dump`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LuaChunk {
+    pub header: LuaHeader,
+    pub main: LuaFunction,
+}
+
+impl LuaChunk {
+    pub fn parse(data: &[u8]) -> Result<Self, LuaChunkError> {
+        let header = Self::parse_header(data)?;
+        let mut reader = ChunkReader { data, pos: HEADER_LEN, header };
+        let main = reader.function(0)?;
+        Ok(Self { header, main })
+    }
+
+    /// Reads and checks the header. Only 5.1 chunks with type sizes the parser can handle pass.
+    pub fn parse_header(data: &[u8]) -> Result<LuaHeader, LuaChunkError> {
+        let header = LuaHeader::read(data)?;
+        header.check()?;
+        Ok(header)
+    }
+
+    /// A chunk without line info has been stripped of all debug info, e.g. by `luac -s`.
+    pub fn is_stripped(&self) -> bool {
+        self.main.line_info.is_empty() && !self.main.code.is_empty()
+    }
+
+    /// The main function and all nested functions.
+    pub fn function_count(&self) -> u32 {
+        fn count(function: &LuaFunction) -> u32 {
+            1 + function.functions.iter().map(count).sum::<u32>()
+        }
+        count(&self.main)
+    }
+}
+
+impl LuaHeader {
+    /// Reads the header fields without checking them, so unsupported chunks can still be
+    /// described. Fails only for data that is not Lua bytecode or is too short.
+    pub fn read(data: &[u8]) -> Result<Self, LuaChunkError> {
+        if data.starts_with(LUAJIT_SIGNATURE) {
+            return Err(LuaChunkError::LuaJit { version: data.get(3).copied().unwrap_or(0) });
+        }
+        if !data.starts_with(SIGNATURE) {
+            return Err(LuaChunkError::NotLuaChunk);
+        }
+        if data.len() < HEADER_LEN {
+            return Err(LuaChunkError::Truncated { offset: data.len(), needed: HEADER_LEN - data.len() });
+        }
+
+        Ok(Self {
+            version: data[4],
+            format: data[5],
+            little_endian: data[6] == 1,
+            int_size: data[7],
+            size_t_size: data[8],
+            instruction_size: data[9],
+            number_size: data[10],
+            integral: data[11] != 0,
+        })
+    }
+
+    pub fn check(&self) -> Result<(), LuaChunkError> {
+        if self.version != VERSION_51 {
+            return Err(LuaChunkError::UnsupportedVersion { version: self.version });
+        }
+        if self.format != 0 {
+            return Err(LuaChunkError::UnsupportedFormat { format: self.format });
+        }
+        let number_ok = if self.integral {
+            matches!(self.number_size, 1 | 2 | 4 | 8)
+        } else {
+            matches!(self.number_size, 4 | 8)
+        };
+        if !matches!(self.int_size, 4 | 8) || !matches!(self.size_t_size, 4 | 8) || self.instruction_size != 4 || !number_ok {
+            return Err(LuaChunkError::UnsupportedSizes {
+                int_size: self.int_size,
+                size_t_size: self.size_t_size,
+                instruction_size: self.instruction_size,
+                number_size: self.number_size,
+            });
+        }
+        Ok(())
+    }
+}
+
+struct ChunkReader<'a> {
+    data: &'a [u8],
+    pos: usize,
+    header: LuaHeader,
+}
+
+impl<'a> ChunkReader<'a> {
+    fn function(&mut self, depth: usize) -> Result<LuaFunction, LuaChunkError> {
+        if depth > MAX_DEPTH {
+            return Err(LuaChunkError::TooDeep);
+        }
+
+        let source = self.string()?;
+        let line_defined = self.int()? as u32;
+        let last_line_defined = self.int()? as u32;
+        let upvalue_count = self.byte()?;
+        let param_count = self.byte()?;
+        let vararg_flags = self.byte()?;
+        let max_stack_size = self.byte()?;
+
+        let count = self.count(4)?;
+        let code = (0..count).map(|_| self.uint(4).map(|i| i as u32)).collect::<Result<_, _>>()?;
+
+        let count = self.count(1)?;
+        let constants = (0..count).map(|_| self.constant()).collect::<Result<_, _>>()?;
+
+        let count = self.count(1)?;
+        let functions = (0..count).map(|_| self.function(depth + 1)).collect::<Result<_, _>>()?;
+
+        let int_size = self.header.int_size as usize;
+        let count = self.count(int_size)?;
+        let line_info = (0..count).map(|_| self.int().map(|line| line as u32)).collect::<Result<_, _>>()?;
+
+        let count = self.count(self.header.size_t_size as usize + 2 * int_size)?;
+        let locals = (0..count)
+            .map(|_| {
+                Ok(LuaLocal {
+                    name: self.string()?.unwrap_or_default(),
+                    start_pc: self.int()? as u32,
+                    end_pc: self.int()? as u32,
+                })
+            })
+            .collect::<Result<_, _>>()?;
+
+        let count = self.count(self.header.size_t_size as usize)?;
+        let upvalue_names = (0..count)
+            .map(|_| self.string().map(Option::unwrap_or_default))
+            .collect::<Result<_, _>>()?;
+
+        Ok(LuaFunction {
+            source,
+            line_defined,
+            last_line_defined,
+            upvalue_count,
+            param_count,
+            vararg_flags,
+            max_stack_size,
+            code,
+            constants,
+            functions,
+            line_info,
+            locals,
+            upvalue_names,
+        })
+    }
+
+    fn constant(&mut self) -> Result<LuaConstant, LuaChunkError> {
+        let offset = self.pos;
+        Ok(match self.byte()? {
+            0 => LuaConstant::Nil,
+            1 => LuaConstant::Boolean(self.byte()? != 0),
+            3 => self.number()?,
+            4 => LuaConstant::String(self.string()?.unwrap_or_default()),
+            tag => return Err(LuaChunkError::InvalidConstant { offset, tag }),
+        })
+    }
+
+    fn number(&mut self) -> Result<LuaConstant, LuaChunkError> {
+        let size = self.header.number_size as usize;
+        let raw = self.uint(size)?;
+        Ok(match (self.header.integral, size) {
+            // Sign-extend the stored integer to 64 bits.
+            (true, _) => LuaConstant::Integer((raw << (64 - size * 8)) as i64 >> (64 - size * 8)),
+            (false, 4) => LuaConstant::Number(f32::from_bits(raw as u32) as f64),
+            (false, _) => LuaConstant::Number(f64::from_bits(raw)),
+        })
+    }
+
+    /// A length-prefixed string; the stored length includes a trailing NUL, 0 means no string.
+    fn string(&mut self) -> Result<Option<Vec<u8>>, LuaChunkError> {
+        let len = self.uint(self.header.size_t_size as usize)? as usize;
+        if len == 0 {
+            return Ok(None);
+        }
+        let bytes = self.take(len)?;
+        Ok(Some(bytes[..len - 1].to_vec()))
+    }
+
+    /// An element count, checked against the bytes left so corrupt counts fail before allocating.
+    fn count(&mut self, min_item_size: usize) -> Result<usize, LuaChunkError> {
+        let offset = self.pos;
+        let count = self.int()? as usize;
+        let remaining = self.data.len() - self.pos;
+        if count.saturating_mul(min_item_size) > remaining {
+            return Err(LuaChunkError::Truncated { offset, needed: count.saturating_mul(min_item_size) });
+        }
+        Ok(count)
+    }
+
+    fn int(&mut self) -> Result<u64, LuaChunkError> {
+        self.uint(self.header.int_size as usize)
+    }
+
+    fn byte(&mut self) -> Result<u8, LuaChunkError> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn uint(&mut self, size: usize) -> Result<u64, LuaChunkError> {
+        let little_endian = self.header.little_endian;
+        let bytes = self.take(size)?;
+        let fold = |value: u64, &byte: &u8| (value << 8) | byte as u64;
+        Ok(if little_endian {
+            bytes.iter().rev().fold(0, fold)
+        } else {
+            bytes.iter().fold(0, fold)
+        })
+    }
+
+    fn take(&mut self, len: usize) -> Result<&'a [u8], LuaChunkError> {
+        let end = self.pos.checked_add(len).filter(|&end| end <= self.data.len());
+        let Some(end) = end else {
+            return Err(LuaChunkError::Truncated { offset: self.pos, needed: len });
+        };
+        let bytes = &self.data[self.pos..end];
+        self.pos = end;
+        Ok(bytes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::processing::test_support::lua_chunk;
+
+    #[test]
+    fn reads_header_and_detects_stripped_chunks() {
+        let chunk = LuaChunk::parse(&lua_chunk(false)).unwrap();
+        let expected = LuaHeader {
+            version: 0x51,
+            format: 0,
+            little_endian: true,
+            int_size: 4,
+            size_t_size: 4,
+            instruction_size: 4,
+            number_size: 8,
+            integral: false,
+        };
+        assert_eq!(chunk.header, expected);
+        assert_eq!(chunk.function_count(), 2);
+        assert!(!chunk.is_stripped());
+        assert!(LuaChunk::parse(&lua_chunk(true)).unwrap().is_stripped());
+    }
+
+    #[test]
+    fn rejects_other_versions_and_luajit() {
+        let mut data = lua_chunk(false);
+        data[4] = 0x53;
+        assert_eq!(LuaHeader::read(&data).unwrap().version, 0x53);
+        let error = LuaChunk::parse_header(&data).unwrap_err();
+        assert_eq!(error.to_string(), "Lua version 5.3 is not supported, only 5.1");
+
+        let error = LuaChunk::parse(b"\x1bLJ\x02\x00\x00").unwrap_err();
+        assert_eq!(error, LuaChunkError::LuaJit { version: 2 });
+
+        data[4] = 0x51;
+        data[7] = 2;
+        assert!(matches!(LuaChunk::parse(&data), Err(LuaChunkError::UnsupportedSizes { int_size: 2, .. })));
+    }
+}
@@ -0,0 +1,313 @@
+use std::fmt::Write as _;
+use crate::processing::lua_chunk::{LuaChunk, LuaChunkError, LuaConstant, LuaFunction};
+
+/// Lua 5.1 opcodes in encoding order.
+const OPCODES: [&str; 38] = [
+    "MOVE", "LOADK", "LOADBOOL", "LOADNIL", "GETUPVAL", "GETGLOBAL", "GETTABLE", "SETGLOBAL", "SETUPVAL",
+    "SETTABLE", "NEWTABLE", "SELF", "ADD", "SUB", "MUL", "DIV", "MOD", "POW", "UNM", "NOT", "LEN", "CONCAT",
+    "JMP", "EQ", "LT", "LE", "TEST", "TESTSET", "CALL", "TAILCALL", "RETURN", "FORLOOP", "FORPREP",
+    "TFORLOOP", "SETLIST", "CLOSE", "CLOSURE", "VARARG",
+];
+
+/// Set on a B or C operand that refers to a constant instead of a register.
+const BIT_RK: u32 = 1 << 8;
+const MAX_SBX: i32 = (1 << 17) - 1;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Mode {
+    Abc,
+    ABx,
+    AsBx,
+}
+
+/// How an operand is used, as in `lopcodes.c`: unused, plain value, register, or register/constant.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Arg {
+    N,
+    U,
+    R,
+    K,
+}
+
+fn op_mode(op: usize) -> (Mode, Arg, Arg) {
+    use Arg::*;
+    match OPCODES[op] {
+        "MOVE" | "LOADNIL" | "UNM" | "NOT" | "LEN" => (Mode::Abc, R, N),
+        "LOADK" | "GETGLOBAL" | "SETGLOBAL" => (Mode::ABx, K, N),
+        "LOADBOOL" | "NEWTABLE" | "CALL" | "TAILCALL" | "SETLIST" => (Mode::Abc, U, U),
+        "GETUPVAL" | "SETUPVAL" | "RETURN" | "VARARG" => (Mode::Abc, U, N),
+        "GETTABLE" | "SELF" => (Mode::Abc, R, K),
+        "SETTABLE" | "ADD" | "SUB" | "MUL" | "DIV" | "MOD" | "POW" | "EQ" | "LT" | "LE" => (Mode::Abc, K, K),
+        "CONCAT" => (Mode::Abc, R, R),
+        "JMP" | "FORLOOP" | "FORPREP" => (Mode::AsBx, R, N),
+        "TEST" | "TESTSET" => (Mode::Abc, R, U),
+        "TFORLOOP" => (Mode::Abc, N, U),
+        "CLOSURE" => (Mode::ABx, U, N),
+        _ => (Mode::Abc, N, N),
+    }
+}
+
+/// Produces a `luac -l -l` style listing of Lua 5.1 bytecode, for chunks the decompiler
+/// cannot turn back into source.
+pub struct LuaDisassembler;
+
+impl LuaDisassembler {
+    pub fn disassemble_bytes(data: &[u8]) -> Result<String, LuaChunkError> {
+        LuaChunk::parse(data).map(|chunk| Self::disassemble(&chunk))
+    }
+
+    /// Lists every function: header, instructions with line numbers, constants, locals and
+    /// upvalues. Nested functions follow their parent and are named by their path, e.g. `main.0.2`.
+    pub fn disassemble(chunk: &LuaChunk) -> String {
+        let mut out = String::new();
+        let source = chunk.main.source.as_deref().unwrap_or(b"?");
+        Self::function(&mut out, &chunk.main, "main", source);
+        out
+    }
+
+    /// Text for a chunk that failed to decompile: the error followed by the disassembly, all as
+    /// Lua comments so the result is still a valid `.lua` file.
+    pub fn fallback_listing(error: &str, data: &[u8]) -> String {
+        let mut out = format!("-- Decompile Error: {error}\n");
+        match Self::disassemble_bytes(data) {
+            Ok(listing) => {
+                out.push_str("-- Disassembly:\n");
+                for line in listing.lines() {
+                    out.push_str("-- ");
+                    out.push_str(line);
+                    out.push('\n');
+                }
+            }
+            Err(e) => {
+                let _ = writeln!(out, "-- Disassembly Error: {e}");
+            }
+        }
+        out
+    }
+
+    fn function(out: &mut String, function: &LuaFunction, name: &str, parent_source: &[u8]) {
+        let source = function.source.as_deref().unwrap_or(parent_source);
+        let display_source = match source.first() {
+            Some(b'@' | b'=') => &source[1..],
+            _ => source,
+        };
+
+        let _ = writeln!(
+            out,
+            "{} <{}:{},{}> ({})",
+            if name == "main" { name.to_owned() } else { format!("function {name}") },
+            String::from_utf8_lossy(display_source),
+            function.line_defined,
+            function.last_line_defined,
+            plural(function.code.len(), "instruction")
+        );
+        let _ = writeln!(
+            out,
+            "{}{} param{}, {}, {}, {}, {}, {}",
+            function.param_count,
+            if function.vararg_flags != 0 { "+" } else { "" },
+            if function.param_count == 1 { "" } else { "s" },
+            plural(function.max_stack_size as usize, "slot"),
+            plural(function.upvalue_count as usize, "upvalue"),
+            plural(function.locals.len(), "local"),
+            plural(function.constants.len(), "constant"),
+            plural(function.functions.len(), "function")
+        );
+
+        for (pc, &instruction) in function.code.iter().enumerate() {
+            Self::instruction(out, function, name, pc, instruction);
+        }
+
+        let _ = writeln!(out, "constants ({}):", function.constants.len());
+        for (i, constant) in function.constants.iter().enumerate() {
+            let _ = writeln!(out, "\t{}\t{}", i + 1, constant_text(constant));
+        }
+        let _ = writeln!(out, "locals ({}):", function.locals.len());
+        for (i, local) in function.locals.iter().enumerate() {
+            let _ = writeln!(
+                out,
+                "\t{}\t{}\t{}\t{}",
+                i,
+                String::from_utf8_lossy(&local.name),
+                local.start_pc + 1,
+                local.end_pc + 1
+            );
+        }
+        let _ = writeln!(out, "upvalues ({}):", function.upvalue_names.len());
+        for (i, upvalue) in function.upvalue_names.iter().enumerate() {
+            let _ = writeln!(out, "\t{}\t{}", i, String::from_utf8_lossy(upvalue));
+        }
+
+        for (i, child) in function.functions.iter().enumerate() {
+            out.push('\n');
+            Self::function(out, child, &format!("{name}.{i}"), source);
+        }
+    }
+
+    fn instruction(out: &mut String, function: &LuaFunction, name: &str, pc: usize, instruction: u32) {
+        let op = (instruction & 0x3F) as usize;
+        let a = (instruction >> 6) & 0xFF;
+        let c = (instruction >> 14) & 0x1FF;
+        let b = (instruction >> 23) & 0x1FF;
+        let bx = instruction >> 14;
+        let sbx = bx as i32 - MAX_SBX;
+
+        let line = match function.line_info.get(pc) {
+            Some(line) => line.to_string(),
+            None => "-".to_owned(),
+        };
+        let _ = write!(out, "\t{}\t[{}]\t", pc + 1, line);
+
+        if op >= OPCODES.len() {
+            let _ = writeln!(out, "{:<9}\t{:#010x}", "???", instruction);
+            return;
+        }
+
+        let opcode = OPCODES[op];
+        let (mode, b_arg, c_arg) = op_mode(op);
+        let operand = |value: u32, arg: Arg| {
+            if arg == Arg::K && value & BIT_RK != 0 {
+                format!(" {}", -1 - (value & !BIT_RK) as i64)
+            } else {
+                format!(" {value}")
+            }
+        };
+        let operands = match mode {
+            Mode::Abc => {
+                let mut text = a.to_string();
+                if b_arg != Arg::N {
+                    text.push_str(&operand(b, b_arg));
+                }
+                if c_arg != Arg::N {
+                    text.push_str(&operand(c, c_arg));
+                }
+                text
+            }
+            Mode::ABx if b_arg == Arg::K => format!("{a} {}", -1 - bx as i64),
+            Mode::ABx => format!("{a} {bx}"),
+            Mode::AsBx if opcode == "JMP" => sbx.to_string(),
+            Mode::AsBx => format!("{a} {sbx}"),
+        };
+
+        let constant = |index: u32| match function.constants.get(index as usize) {
+            Some(constant) => constant_text(constant),
+            None => "?".to_owned(),
+        };
+        let rk = |value: u32| if value & BIT_RK != 0 { constant(value & !BIT_RK) } else { "-".to_owned() };
+
+        let comment = match opcode {
+            "LOADK" => Some(constant(bx)),
+            "GETGLOBAL" | "SETGLOBAL" => Some(match function.constants.get(bx as usize) {
+                Some(LuaConstant::String(s)) => String::from_utf8_lossy(s).into_owned(),
+                Some(other) => constant_text(other),
+                None => "?".to_owned(),
+            }),
+            "GETUPVAL" | "SETUPVAL" => Some(match function.upvalue_names.get(b as usize) {
+                Some(upvalue) => String::from_utf8_lossy(upvalue).into_owned(),
+                None => "-".to_owned(),
+            }),
+            "GETTABLE" | "SELF" if c & BIT_RK != 0 => Some(constant(c & !BIT_RK)),
+            "SETTABLE" | "ADD" | "SUB" | "MUL" | "DIV" | "MOD" | "POW" | "EQ" | "LT" | "LE"
+                if (b | c) & BIT_RK != 0 =>
+            {
+                Some(format!("{} {}", rk(b), rk(c)))
+            }
+            "JMP" | "FORLOOP" | "FORPREP" => Some(format!("to {}", pc as i64 + 2 + sbx as i64)),
+            "CLOSURE" => Some(format!("{name}.{bx}")),
+            _ => None,
+        };
+
+        let _ = write!(out, "{opcode:<9}\t{operands}");
+        if let Some(comment) = comment {
+            let _ = write!(out, "\t; {comment}");
+        }
+        out.push('\n');
+    }
+}
+
+fn plural(count: usize, noun: &str) -> String {
+    format!("{count} {noun}{}", if count == 1 { "" } else { "s" })
+}
+
+fn constant_text(constant: &LuaConstant) -> String {
+    match constant {
+        LuaConstant::Nil => "nil".to_owned(),
+        LuaConstant::Boolean(value) => value.to_string(),
+        LuaConstant::Number(value) => value.to_string(),
+        LuaConstant::Integer(value) => value.to_string(),
+        LuaConstant::String(bytes) => quote(bytes),
+    }
+}
+
+/// Quotes a string constant with Lua escapes. Valid UTF-8 text is kept readable; other
+/// non-printable bytes become `\ddd`, always three digits so a following digit is not absorbed.
+fn quote(bytes: &[u8]) -> String {
+    let mut out = String::with_capacity(bytes.len() + 2);
+    out.push('"');
+    for chunk in bytes.utf8_chunks() {
+        for c in chunk.valid().chars() {
+            match c {
+                '"' => out.push_str("\\\""),
+                '\\' => out.push_str("\\\\"),
+                '\n' => out.push_str("\\n"),
+                '\r' => out.push_str("\\r"),
+                '\t' => out.push_str("\\t"),
+                c if c.is_control() => {
+                    for byte in c.encode_utf8(&mut [0; 4]).bytes() {
+                        let _ = write!(out, "\\{byte:03}");
+                    }
+                }
+                c => out.push(c),
+            }
+        }
+        for byte in chunk.invalid() {
+            let _ = write!(out, "\\{byte:03}");
+        }
+    }
+    out.push('"');
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::processing::test_support::lua_chunk;
+
+    #[test]
+    fn parses_and_lists_functions() {
+        let chunk = LuaChunk::parse(&lua_chunk(false)).unwrap();
+        assert_eq!(chunk.main.constants[2], LuaConstant::Number(2.0));
+        assert_eq!(chunk.main.functions.len(), 1);
+
+        let listing = LuaDisassembler::disassemble(&chunk);
+        assert!(listing.starts_with("main <test.lua:0,0> (8 instructions)\n0+ params, 4 slots, 0 upvalues, 1 local, 3 constants, 1 function\n"));
+        assert!(listing.contains("\t1\t[1]\tLOADK    \t0 -1\t; \"hi\\n\"\n"));
+        assert!(listing.contains("\t2\t[1]\tGETGLOBAL\t1 -2\t; print\n"));
+        assert!(listing.contains("\t5\t[1]\tCALL     \t1 3 1\n"));
+        assert!(listing.contains("\t6\t[5]\tCLOSURE  \t1 0\t; main.0\n"));
+        assert!(listing.contains("\t7\t[6]\tJMP      \t0\t; to 8\n"));
+        assert!(listing.contains("\t0\ts\t2\t8\n"));
+        assert!(listing.contains("function main.0 <test.lua:3,5> (1 instruction)\n"));
+    }
+
+    #[test]
+    fn lists_stripped_chunks_and_reports_errors() {
+        let listing = LuaDisassembler::disassemble_bytes(&lua_chunk(true)).unwrap();
+        assert!(listing.contains("\t1\t[-]\tLOADK    \t0 -1\t; \"hi\\n\"\n"));
+
+        let data = lua_chunk(false);
+        assert!(matches!(LuaChunk::parse(&data[..60]), Err(LuaChunkError::Truncated { .. })));
+        assert_eq!(LuaChunk::parse(b"-- source"), Err(LuaChunkError::NotLuaChunk));
+
+        let fallback = LuaDisassembler::fallback_listing("bad jump", &data);
+        assert!(fallback.starts_with("-- Decompile Error: bad jump\n-- Disassembly:\n-- main <"));
+        assert!(fallback.lines().all(|line| line.starts_with("--")));
+    }
+
+    #[test]
+    fn quotes_escapes_with_three_digits() {
+        assert_eq!(quote(b"\x012"), "\"\\0012\"");
+        assert_eq!(quote(b"a\xFF7\"\\\n"), "\"a\\2557\\\"\\\\\\n\"");
+        assert_eq!(quote("\u{85}é".as_bytes()), "\"\\194\\133é\"");
+    }
+}
@@ -1,2 +1,22 @@
 ﻿pub mod raw_ice;
-pub mod bdo_decomp;
\ No newline at end of file
+pub mod raw_ice_simd;
+pub mod ice_stream;
+pub mod bdo_decomp;
+pub mod bdo_stream;
+pub mod paz_entry;
+pub mod file_sniffer;
+pub mod entry_detector;
+pub mod entry_reader;
+pub mod texture_export;
+pub mod texture_preview;
+pub mod texture_encode;
+pub mod thumbnails;
+pub mod texture_catalog;
+pub mod dds_format;
+pub mod dds_repair;
+pub mod lua_chunk;
+pub mod lua_disassembler;
+pub mod lua_bulk;
+pub mod loc_table;
+#[cfg(test)]
+pub(crate) mod test_support;
\ No newline at end of file
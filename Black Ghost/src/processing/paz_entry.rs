@@ -0,0 +1,265 @@
+use std::fmt;
+use std::fs::File;
+use std::io;
+use std::path::PathBuf;
+use memmap2::Mmap;
+use crate::BDO_ICE_KEY;
+use crate::models::entry_layout::EntryLayout;
+use crate::models::paz_file::PazFile;
+use crate::processing::bdo_decomp::{BdoDecomp, BdoDecompError};
+use crate::processing::entry_detector::EntryDetector;
+use crate::processing::raw_ice::RawIce;
+
+#[derive(Debug)]
+pub enum EntryReadError {
+    /// The `.paz` file is missing or cannot be mapped.
+    PazUnavailable { paz_number: u32 },
+    /// The entry reaches past the end of its `.paz` file.
+    OutOfBounds { paz_number: u32, offset: u32, size: u32 },
+    Decompress { hash: u32, source: BdoDecompError },
+    /// Decoding a range of a large entry through an `EntryReader` failed.
+    Stream { hash: u32, source: io::Error },
+}
+
+impl fmt::Display for EntryReadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::PazUnavailable { paz_number } => write!(f, "cannot open pad{paz_number:05}.paz"),
+            Self::OutOfBounds { paz_number, offset, size } => {
+                write!(f, "entry at offset {offset} with {size} bytes is outside pad{paz_number:05}.paz")
+            }
+            Self::Decompress { hash, source } => write!(f, "cannot decompress entry {hash:08X}: {source}"),
+            Self::Stream { hash, source } => write!(f, "cannot stream entry {hash:08X}: {source}"),
+        }
+    }
+}
+
+impl std::error::Error for EntryReadError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Decompress { source, .. } => Some(source),
+            Self::Stream { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
+
+impl EntryReadError {
+    pub fn out_of_bounds(file_info: &PazFile) -> Self {
+        Self::OutOfBounds {
+            paz_number: file_info.paz_number,
+            offset: file_info.offset,
+            size: file_info.compressed_size,
+        }
+    }
+}
+
+pub struct PazEntry;
+
+impl PazEntry {
+    pub fn map_paz(paz_folder_path: &str, paz_number: u32) -> Option<Mmap> {
+        let paz_name = format!("pad{:05}.paz", paz_number);
+        let full_path = PathBuf::from(paz_folder_path).join(paz_name);
+
+        let file = File::open(full_path).ok()?;
+        unsafe { Mmap::map(&file).ok() }
+    }
+
+    pub fn stored_bytes<'a>(paz_data: &'a [u8], file_info: &PazFile) -> Option<&'a [u8]> {
+        let start = file_info.offset as usize;
+        let end = start + file_info.compressed_size as usize;
+        if end > paz_data.len() { return None; }
+
+        Some(&paz_data[start..end])
+    }
+
+    /// Container flag bytes are 0x6C with the compressed (0x01) and long header (0x02) bits.
+    pub fn is_container_flag(flags: u8) -> bool {
+        (flags & 0xFC) == 0x6C
+    }
+
+    /// Copies at most `max_len` stored bytes and decrypts the 8-byte aligned part of them.
+    pub fn decrypt_prefix(stored: &[u8], max_len: usize) -> Vec<u8> {
+        let mut prefix = stored[..stored.len().min(max_len)].to_vec();
+        Self::decrypt_in_place(&mut prefix);
+        prefix
+    }
+
+    /// Decrypts all whole ICE blocks; a trailing partial block is stored unencrypted.
+    pub fn decrypt_in_place(data: &mut [u8]) {
+        let aligned = data.len() - (data.len() % 8);
+        if aligned == 0 {
+            return;
+        }
+
+        let ice = RawIce::new(0, &BDO_ICE_KEY);
+        if aligned > 8192 {
+            ice.decrypt_par(&mut data[..aligned]);
+        } else {
+            ice.decrypt(&mut data[..aligned]);
+        }
+    }
+
+    pub fn read(paz_folder_path: &str, file_info: &PazFile) -> Result<Vec<u8>, EntryReadError> {
+        let paz_number = file_info.paz_number;
+        let mmap = Self::map_paz(paz_folder_path, paz_number).ok_or(EntryReadError::PazUnavailable { paz_number })?;
+        let stored = Self::stored_bytes(&mmap, file_info).ok_or_else(|| EntryReadError::out_of_bounds(file_info))?;
+
+        Self::decode(stored, file_info).map_err(|source| EntryReadError::Decompress { hash: file_info.hash, source })
+    }
+
+    pub fn decode(stored: &[u8], file_info: &PazFile) -> Result<Vec<u8>, BdoDecompError> {
+        let layout = EntryDetector::detect(stored, file_info);
+        Self::decode_with(stored, file_info, &layout)
+    }
+
+    pub fn decode_with(stored: &[u8], file_info: &PazFile, layout: &EntryLayout) -> Result<Vec<u8>, BdoDecompError> {
+        let mut data = stored.to_vec();
+
+        if layout.is_encrypted {
+            Self::decrypt_in_place(&mut data);
+        }
+
+        if layout.has_container {
+            let mut decompressed_buffer = vec![0u8; file_info.original_size as usize];
+            let actual_size = BdoDecomp::decompress_strict(&data, &mut decompressed_buffer)?;
+            decompressed_buffer.truncate(actual_size as usize);
+            Ok(decompressed_buffer)
+        } else {
+            let limit = file_info.original_size as usize;
+            if data.len() > limit {
+                data.truncate(limit);
+            }
+            Ok(data)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::entry_layout::DetectionStatus;
+    use crate::processing::test_support::{encrypt_entry, padded, paz_file, short_container, Lcg};
+
+    const ABCD_EXPECTED: &[u8] = b"abcdabcdabcdxyz";
+
+    /// 3-byte header, four literals, an 8 byte match at distance 4 and a 3 byte literal tail.
+    const SHORT_COMPRESSED: [u8; 20] = [
+        0x6D, 20, 15,
+        0x30, 0x00, 0x00, 0x00,
+        b'a', b'b', b'c', b'd',
+        0x16, 0x01,
+        0x00, 0x00, 0x00, 0x80,
+        b'x', b'y', b'z',
+    ];
+
+    fn long_header(flags: u8, payload: &[u8], original_size: usize) -> Vec<u8> {
+        let mut blob = vec![flags];
+        blob.extend_from_slice(&((payload.len() + 9) as u32).to_le_bytes());
+        blob.extend_from_slice(&(original_size as u32).to_le_bytes());
+        blob.extend_from_slice(payload);
+        blob
+    }
+
+    #[test]
+    fn decodes_plain_short_compressed_header() {
+        let file_info = paz_file(&SHORT_COMPRESSED, ABCD_EXPECTED.len());
+        let layout = EntryDetector::detect(&SHORT_COMPRESSED, &file_info);
+
+        assert!(layout.has_container && layout.is_compressed() && !layout.has_long_header());
+        assert!(!layout.is_encrypted);
+        assert_eq!(PazEntry::decode(&SHORT_COMPRESSED, &file_info).unwrap(), ABCD_EXPECTED);
+    }
+
+    #[test]
+    fn decodes_encrypted_short_compressed_header() {
+        let stored = encrypt_entry(&padded(SHORT_COMPRESSED.to_vec()));
+        let file_info = paz_file(&stored, ABCD_EXPECTED.len());
+        let layout = EntryDetector::detect(&stored, &file_info);
+
+        assert!(layout.is_encrypted && layout.is_compressed());
+        assert_eq!(PazEntry::decode(&stored, &file_info).unwrap(), ABCD_EXPECTED);
+    }
+
+    #[test]
+    fn decodes_encrypted_short_stored_header() {
+        let content = b"short lua";
+        let mut blob = vec![0x6C, (content.len() + 3) as u8, content.len() as u8];
+        blob.extend_from_slice(content);
+
+        let stored = encrypt_entry(&padded(blob));
+        let file_info = paz_file(&stored, content.len());
+        let layout = EntryDetector::detect(&stored, &file_info);
+
+        assert!(layout.has_container && !layout.is_compressed());
+        assert_eq!(PazEntry::decode(&stored, &file_info).unwrap(), content);
+    }
+
+    #[test]
+    fn decodes_encrypted_long_compressed_header() {
+        let blob = long_header(0x6F, &SHORT_COMPRESSED[3..], ABCD_EXPECTED.len());
+        let stored = encrypt_entry(&padded(blob));
+        let file_info = paz_file(&stored, ABCD_EXPECTED.len());
+        let layout = EntryDetector::detect(&stored, &file_info);
+
+        assert!(layout.is_encrypted && layout.is_compressed() && layout.has_long_header());
+        assert_eq!(PazEntry::decode(&stored, &file_info).unwrap(), ABCD_EXPECTED);
+    }
+
+    #[test]
+    fn returns_decompression_errors() {
+        let file_info = paz_file(&SHORT_COMPRESSED, ABCD_EXPECTED.len());
+        let layout = EntryDetector::detect(&SHORT_COMPRESSED, &file_info);
+
+        assert!(matches!(
+            PazEntry::decode_with(&SHORT_COMPRESSED[..14], &file_info, &layout),
+            Err(BdoDecompError::TruncatedData { .. })
+        ));
+    }
+
+    #[test]
+    fn rejects_containers_ending_short_of_their_size() {
+        let (container, expected) = short_container(&mut Lcg(0x35), 2000);
+        let stored = encrypt_entry(&padded(container));
+        let file_info = paz_file(&stored, expected.len());
+
+        assert!(EntryDetector::detect(&stored, &file_info).has_container);
+        assert!(matches!(
+            PazEntry::decode(&stored, &file_info),
+            Err(BdoDecompError::SizeMismatch { .. })
+        ));
+    }
+
+    #[test]
+    fn keeps_unaligned_tail_unencrypted() {
+        let content = b"<?xml version=\"1.0\"?><root/>\n";
+        let stored = encrypt_entry(content);
+        let file_info = paz_file(&stored, content.len());
+
+        assert!(EntryDetector::detect(&stored, &file_info).is_encrypted);
+        assert_eq!(&stored[24..], &content[24..]);
+        assert_eq!(PazEntry::decode(&stored, &file_info).unwrap(), content);
+    }
+
+    #[test]
+    fn leaves_plain_entries_untouched() {
+        let content = b"DDS \x7c\x00\x00\x00 plain stored texture bytes";
+        let file_info = paz_file(content, content.len());
+        let layout = EntryDetector::detect(content, &file_info);
+
+        assert!(!layout.is_encrypted && !layout.has_container);
+        assert_eq!(PazEntry::decode(content, &file_info).unwrap(), content);
+    }
+
+    #[test]
+    fn leaves_unrecognised_entries_stored_as_is() {
+        let mut rng = Lcg(0x28);
+        let content: Vec<u8> = (0..64).map(|_| rng.next() as u8 | 0x80).collect();
+        let file_info = paz_file(&content, content.len());
+        let layout = EntryDetector::detect(&content, &file_info);
+
+        assert_eq!(layout.status, DetectionStatus::Uncertain);
+        assert!(!layout.is_encrypted && !layout.has_container);
+        assert_eq!(PazEntry::decode(&content, &file_info).unwrap(), content);
+    }
+}
@@ -1,5 +1,7 @@
 use rayon::prelude::*;
 use std::slice;
+#[cfg(target_arch = "x86_64")]
+use crate::processing::raw_ice_simd;
 
 #[repr(align(64))]
 struct AlignedSBoxData([u8; 16384]);
@@ -9,7 +11,7 @@ static SBOX_BLOB: AlignedSBoxData =
 #[derive(Clone, Debug, Copy)]
 #[repr(C, align(16))]
 pub struct RawIceSubkey {
-    val: [u32; 3],
+    pub(crate) val: [u32; 3],
 }
 
 #[derive(Clone, Debug)]
@@ -24,6 +26,38 @@ pub struct RawIceKeyStruct {
 pub struct RawIce {
     pub key: RawIceKeyStruct,
     sbox_flat: [u32; 4096],
+    backend: IceBackend,
+}
+
+/// Implementation used for full 64-byte groups of blocks. Partial groups always take the
+/// scalar path, so every backend produces identical output.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum IceBackend {
+    Scalar,
+    Sse41,
+    Avx2,
+}
+
+impl IceBackend {
+    /// Fastest backend the running CPU supports.
+    pub fn detect() -> Self {
+        [IceBackend::Avx2, IceBackend::Sse41]
+            .into_iter()
+            .find(|backend| backend.is_supported())
+            .unwrap_or(IceBackend::Scalar)
+    }
+
+    pub fn is_supported(self) -> bool {
+        match self {
+            IceBackend::Scalar => true,
+            #[cfg(target_arch = "x86_64")]
+            IceBackend::Sse41 => is_x86_feature_detected!("sse4.1"),
+            #[cfg(target_arch = "x86_64")]
+            IceBackend::Avx2 => is_x86_feature_detected!("avx2"),
+            #[cfg(not(target_arch = "x86_64"))]
+            _ => false,
+        }
+    }
 }
 
 const KEYROT: [i32; 16] = [0, 1, 2, 3, 2, 1, 3, 0, 1, 3, 2, 0, 3, 1, 0, 2];
@@ -108,6 +142,7 @@ impl RawIce {
                 keysched: Vec::new(),
             },
             sbox_flat: [0; 4096],
+            backend: IceBackend::detect(),
         };
 
         unsafe {
@@ -115,14 +150,13 @@ impl RawIce {
             std::ptr::copy_nonoverlapping(src_ptr, ik.sbox_flat.as_mut_ptr(), 4096);
         }
 
+        assert_eq!(key.len(), Self::key_len(level));
         if level < 1 {
             ik.key.size = 1;
             ik.key.rounds = 8;
-            assert_eq!(key.len(), 8);
         } else {
             ik.key.size = level;
             ik.key.rounds = level * 16;
-            assert_eq!(key.len(), level * 8);
         }
 
         ik.key.keysched = vec![RawIceSubkey { val: [0; 3] }; ik.key.rounds];
@@ -130,8 +164,43 @@ impl RawIce {
         ik
     }
 
+    /// Key length in bytes: Thin-ICE (level 0) and ICE take 8 bytes, ICE-n takes 8 * n.
+    pub fn key_len(level: usize) -> usize {
+        8 * level.max(1)
+    }
+
+    /// Forces a specific backend, e.g. to compare implementations.
+    ///
+    /// Panics if the running CPU does not support it.
+    pub fn with_backend(mut self, backend: IceBackend) -> Self {
+        assert!(backend.is_supported(), "{backend:?} is not supported on this CPU");
+        self.backend = backend;
+        self
+    }
+
+    pub fn backend(&self) -> IceBackend {
+        self.backend
+    }
+
     #[inline(always)]
     fn crypt_64_core<const ENCRYPT: bool>(&self, chunk: &mut [u8], sbox_ptr: *const u32) {
+        match self.backend {
+            // SAFETY: the backend is only selected after checking CPU support, and callers
+            // always pass 64-byte chunks with the flat S-box table.
+            #[cfg(target_arch = "x86_64")]
+            IceBackend::Avx2 => unsafe {
+                raw_ice_simd::crypt_64_avx2::<ENCRYPT>(&self.key.keysched, chunk, sbox_ptr)
+            },
+            #[cfg(target_arch = "x86_64")]
+            IceBackend::Sse41 => unsafe {
+                raw_ice_simd::crypt_64_sse41::<ENCRYPT>(&self.key.keysched, chunk, sbox_ptr)
+            },
+            _ => self.crypt_64_scalar::<ENCRYPT>(chunk, sbox_ptr),
+        }
+    }
+
+    #[inline(always)]
+    fn crypt_64_scalar<const ENCRYPT: bool>(&self, chunk: &mut [u8], sbox_ptr: *const u32) {
         let mut l = [0u32; 8];
         let mut r = [0u32; 8];
 
@@ -329,9 +398,9 @@ impl RawIce {
         }
     }
 
-    fn key_sched_build(&mut self, kb: &mut [u16; 4], n: i32, keyrot: &[i32]) {
+    fn key_sched_build(&mut self, kb: &mut [u16; 4], n: usize, keyrot: &[i32]) {
         for (i, &kr) in keyrot.iter().enumerate().take(8) {
-            let isk = &mut self.key.keysched[n as usize + i];
+            let isk = &mut self.key.keysched[n + i];
             isk.val = [0; 3];
             for _ in 0..5 {
                 for j in 0..3 {
@@ -360,14 +429,132 @@ impl RawIce {
             let mut kb = [0u16; 4];
             for j in 0..4 {
                 kb[3 - j] =
-                    (key[i * 8 + j * 2] as u16) << 8 | key[i as usize * 8 + j * 2 + 1] as u16;
+                    (key[i * 8 + j * 2] as u16) << 8 | key[i * 8 + j * 2 + 1] as u16;
+            }
+            self.key_sched_build(&mut kb, i * 8, &KEYROT);
+            self.key_sched_build(&mut kb, self.key.rounds - 8 - i * 8, &KEYROT[8..16]);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::processing::test_support::Lcg;
+
+    fn random_bytes(rng: &mut Lcg, len: usize) -> Vec<u8> {
+        (0..len).map(|_| rng.next() as u8).collect()
+    }
+
+    fn hex(text: &str) -> Vec<u8> {
+        (0..text.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&text[i..i + 2], 16).unwrap())
+            .collect()
+    }
+
+    /// Vectors published with the reference ICE implementation.
+    #[test]
+    fn matches_reference_vectors() {
+        let cases = [
+            (0, "deadbeef01234567", "de240d83a00a9cc0"),
+            (1, "deadbeef01234567", "7d6ef1ef30d47a96"),
+            (2, "00112233445566778899aabbccddeeff", "f94840d86972f21c"),
+        ];
+
+        for (level, key, cipher) in cases {
+            let plain = hex("fedcba9876543210");
+            for backend in [IceBackend::Scalar, IceBackend::detect()] {
+                let ice = RawIce::new(level, &hex(key)).with_backend(backend);
+
+                // Pad to a full 64-byte group so the vector also runs through the block cores.
+                let mut data = plain.repeat(9);
+                ice.encrypt(&mut data);
+                for block in data.chunks_exact(8) {
+                    assert_eq!(block, hex(cipher), "level {level}, {backend:?}");
+                }
+
+                ice.decrypt(&mut data);
+                assert_eq!(data, plain.repeat(9), "level {level}, {backend:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn round_trips_random_keys_and_levels() {
+        let mut rng = Lcg(0xC0FFEE);
+        for level in 0..=8 {
+            for _ in 0..8 {
+                let key = random_bytes(&mut rng, RawIce::key_len(level));
+                let ice = RawIce::new(level, &key);
+                let blocks = rng.below(300);
+                let plain = random_bytes(&mut rng, blocks * 8);
+
+                let mut data = plain.clone();
+                ice.encrypt(&mut data);
+                if plain.len() >= 16 {
+                    assert_ne!(data, plain, "level {level} left data unchanged");
+                }
+                ice.decrypt_par(&mut data);
+                assert_eq!(data, plain, "level {level}, {} bytes", plain.len());
+            }
+        }
+    }
+
+    #[test]
+    fn different_keys_give_different_ciphertext() {
+        let mut rng = Lcg(3);
+        for level in 0..=3 {
+            let key = random_bytes(&mut rng, RawIce::key_len(level));
+            let mut other = key.clone();
+            other[rng.below(key.len())] ^= 1 << rng.below(8);
+
+            let plain = random_bytes(&mut rng, 64);
+            let (mut a, mut b) = (plain.clone(), plain.clone());
+            RawIce::new(level, &key).encrypt(&mut a);
+            RawIce::new(level, &other).encrypt(&mut b);
+            assert_ne!(a, b, "level {level}");
+        }
+    }
+
+    #[test]
+    fn simd_backends_match_scalar() {
+        let mut rng = Lcg(0x1CE);
+        let backends: Vec<_> = [IceBackend::Sse41, IceBackend::Avx2]
+            .into_iter()
+            .filter(|backend| backend.is_supported())
+            .collect();
+
+        for level in [0usize, 1, 2] {
+            let key = random_bytes(&mut rng, 8 * level.max(1));
+            let scalar = RawIce::new(level, &key).with_backend(IceBackend::Scalar);
+
+            // Lengths cover every tail path: partial 64-byte groups, 128/256 batches and the
+            // parallel split above 16 KiB.
+            for blocks in [0, 1, 7, 8, 9, 16, 31, 33, 40, 129, 2049, 2100] {
+                let plain = random_bytes(&mut rng, blocks * 8);
+
+                let mut expected = plain.clone();
+                scalar.encrypt(&mut expected);
+
+                for &backend in &backends {
+                    let ice = RawIce::new(level, &key).with_backend(backend);
+
+                    let mut serial = plain.clone();
+                    ice.encrypt(&mut serial);
+                    assert_eq!(serial, expected, "{backend:?} encrypt, level {level}, {blocks} blocks");
+
+                    let mut parallel = plain.clone();
+                    ice.encrypt_par(&mut parallel);
+                    assert_eq!(parallel, expected, "{backend:?} encrypt_par, level {level}, {blocks} blocks");
+
+                    ice.decrypt(&mut serial);
+                    assert_eq!(serial, plain, "{backend:?} decrypt, level {level}, {blocks} blocks");
+
+                    ice.decrypt_par(&mut parallel);
+                    assert_eq!(parallel, plain, "{backend:?} decrypt_par, level {level}, {blocks} blocks");
+                }
             }
-            self.key_sched_build(&mut kb, (i * 8).try_into().unwrap(), &KEYROT);
-            self.key_sched_build(
-                &mut kb,
-                (self.key.rounds - 8 - i * 8).try_into().unwrap(),
-                &KEYROT[8..16],
-            );
         }
     }
 }
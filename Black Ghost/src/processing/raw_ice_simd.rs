@@ -0,0 +1,172 @@
+//! Vectorized versions of `RawIce::crypt_64_core`. Each processes the same eight 64-bit blocks
+//! as the scalar core, one block per 32-bit lane, and must stay bit-identical to it.
+#![cfg(target_arch = "x86_64")]
+
+use std::arch::x86_64::*;
+use crate::processing::raw_ice::RawIceSubkey;
+
+#[inline(always)]
+fn load_halves(chunk: &[u8]) -> ([u32; 8], [u32; 8]) {
+    let mut l = [0u32; 8];
+    let mut r = [0u32; 8];
+    for (i, block) in chunk.chunks_exact(8).enumerate() {
+        l[i] = u32::from_be_bytes(block[0..4].try_into().unwrap());
+        r[i] = u32::from_be_bytes(block[4..8].try_into().unwrap());
+    }
+    (l, r)
+}
+
+#[inline(always)]
+fn store_halves(chunk: &mut [u8], l: &[u32; 8], r: &[u32; 8]) {
+    for (i, block) in chunk.chunks_exact_mut(8).enumerate() {
+        block[0..4].copy_from_slice(&r[i].to_be_bytes());
+        block[4..8].copy_from_slice(&l[i].to_be_bytes());
+    }
+}
+
+#[target_feature(enable = "avx2")]
+fn ice_f_avx2(p: __m256i, sk: &RawIceSubkey, sbox: *const u32) -> __m256i {
+    let low_mask = _mm256_set1_epi32(0x3ff);
+    let high_mask = _mm256_set1_epi32(0xffc00);
+
+    let tr = _mm256_or_si256(
+        _mm256_and_si256(p, low_mask),
+        _mm256_and_si256(_mm256_slli_epi32::<2>(p), high_mask),
+    );
+    let rotated = _mm256_or_si256(_mm256_slli_epi32::<18>(p), _mm256_srli_epi32::<14>(p));
+    let tl = _mm256_or_si256(
+        _mm256_and_si256(_mm256_srli_epi32::<16>(p), low_mask),
+        _mm256_and_si256(rotated, high_mask),
+    );
+
+    let salt = _mm256_and_si256(_mm256_set1_epi32(sk.val[2] as i32), _mm256_xor_si256(tl, tr));
+    let al = _mm256_xor_si256(_mm256_xor_si256(salt, tl), _mm256_set1_epi32(sk.val[0] as i32));
+    let ar = _mm256_xor_si256(_mm256_xor_si256(salt, tr), _mm256_set1_epi32(sk.val[1] as i32));
+
+    let idx0 = _mm256_and_si256(_mm256_srli_epi32::<10>(al), low_mask);
+    let idx1 = _mm256_and_si256(al, low_mask);
+    let idx2 = _mm256_and_si256(_mm256_srli_epi32::<10>(ar), low_mask);
+    let idx3 = _mm256_and_si256(ar, low_mask);
+
+    // SAFETY: every index is masked to 10 bits and each table holds 1024 entries.
+    unsafe {
+        let base = sbox as *const i32;
+        let s0 = _mm256_i32gather_epi32::<4>(base, idx0);
+        let s1 = _mm256_i32gather_epi32::<4>(base.add(1024), idx1);
+        let s2 = _mm256_i32gather_epi32::<4>(base.add(2048), idx2);
+        let s3 = _mm256_i32gather_epi32::<4>(base.add(3072), idx3);
+        _mm256_xor_si256(_mm256_xor_si256(s0, s1), _mm256_xor_si256(s2, s3))
+    }
+}
+
+/// # Safety
+/// The CPU must support AVX2, `chunk` must be 64 bytes and `sbox` the 4096 entry S-box table.
+#[target_feature(enable = "avx2")]
+pub unsafe fn crypt_64_avx2<const ENCRYPT: bool>(keysched: &[RawIceSubkey], chunk: &mut [u8], sbox: *const u32) {
+    let (lw, rw) = load_halves(chunk);
+    let (mut l, mut r) = unsafe {
+        (
+            _mm256_loadu_si256(lw.as_ptr() as *const __m256i),
+            _mm256_loadu_si256(rw.as_ptr() as *const __m256i),
+        )
+    };
+
+    if ENCRYPT {
+        for pair in keysched.chunks_exact(2) {
+            l = _mm256_xor_si256(l, ice_f_avx2(r, &pair[0], sbox));
+            r = _mm256_xor_si256(r, ice_f_avx2(l, &pair[1], sbox));
+        }
+    } else {
+        for pair in keysched.rchunks_exact(2) {
+            l = _mm256_xor_si256(l, ice_f_avx2(r, &pair[1], sbox));
+            r = _mm256_xor_si256(r, ice_f_avx2(l, &pair[0], sbox));
+        }
+    }
+
+    let mut lw = [0u32; 8];
+    let mut rw = [0u32; 8];
+    unsafe {
+        _mm256_storeu_si256(lw.as_mut_ptr() as *mut __m256i, l);
+        _mm256_storeu_si256(rw.as_mut_ptr() as *mut __m256i, r);
+    }
+    store_halves(chunk, &lw, &rw);
+}
+
+#[target_feature(enable = "sse4.1")]
+fn ice_f_sse41(p: __m128i, sk: &RawIceSubkey, sbox: *const u32) -> __m128i {
+    let low_mask = _mm_set1_epi32(0x3ff);
+    let high_mask = _mm_set1_epi32(0xffc00);
+
+    let tr = _mm_or_si128(
+        _mm_and_si128(p, low_mask),
+        _mm_and_si128(_mm_slli_epi32::<2>(p), high_mask),
+    );
+    let rotated = _mm_or_si128(_mm_slli_epi32::<18>(p), _mm_srli_epi32::<14>(p));
+    let tl = _mm_or_si128(
+        _mm_and_si128(_mm_srli_epi32::<16>(p), low_mask),
+        _mm_and_si128(rotated, high_mask),
+    );
+
+    let salt = _mm_and_si128(_mm_set1_epi32(sk.val[2] as i32), _mm_xor_si128(tl, tr));
+    let al = _mm_xor_si128(_mm_xor_si128(salt, tl), _mm_set1_epi32(sk.val[0] as i32));
+    let ar = _mm_xor_si128(_mm_xor_si128(salt, tr), _mm_set1_epi32(sk.val[1] as i32));
+
+    let idx0 = _mm_and_si128(_mm_srli_epi32::<10>(al), low_mask);
+    let idx1 = _mm_add_epi32(_mm_and_si128(al, low_mask), _mm_set1_epi32(1024));
+    let idx2 = _mm_add_epi32(_mm_and_si128(_mm_srli_epi32::<10>(ar), low_mask), _mm_set1_epi32(2048));
+    let idx3 = _mm_add_epi32(_mm_and_si128(ar, low_mask), _mm_set1_epi32(3072));
+
+    // SSE has no gather; the lanes are looked up one by one.
+    macro_rules! lookup {
+        ($lane:literal) => {{
+            // SAFETY: each index is a masked 10-bit value plus its table offset, below 4096.
+            let at = |idx: __m128i| unsafe { *sbox.add(_mm_extract_epi32::<$lane>(idx) as usize) };
+            at(idx0) ^ at(idx1) ^ at(idx2) ^ at(idx3)
+        }};
+    }
+
+    _mm_set_epi32(lookup!(3) as i32, lookup!(2) as i32, lookup!(1) as i32, lookup!(0) as i32)
+}
+
+/// # Safety
+/// The CPU must support SSE4.1, `chunk` must be 64 bytes and `sbox` the 4096 entry S-box table.
+#[target_feature(enable = "sse4.1")]
+pub unsafe fn crypt_64_sse41<const ENCRYPT: bool>(keysched: &[RawIceSubkey], chunk: &mut [u8], sbox: *const u32) {
+    let (lw, rw) = load_halves(chunk);
+    let (mut l, mut r) = unsafe {
+        (
+            [
+                _mm_loadu_si128(lw.as_ptr() as *const __m128i),
+                _mm_loadu_si128(lw.as_ptr().add(4) as *const __m128i),
+            ],
+            [
+                _mm_loadu_si128(rw.as_ptr() as *const __m128i),
+                _mm_loadu_si128(rw.as_ptr().add(4) as *const __m128i),
+            ],
+        )
+    };
+
+    for half in 0..2 {
+        if ENCRYPT {
+            for pair in keysched.chunks_exact(2) {
+                l[half] = _mm_xor_si128(l[half], ice_f_sse41(r[half], &pair[0], sbox));
+                r[half] = _mm_xor_si128(r[half], ice_f_sse41(l[half], &pair[1], sbox));
+            }
+        } else {
+            for pair in keysched.rchunks_exact(2) {
+                l[half] = _mm_xor_si128(l[half], ice_f_sse41(r[half], &pair[1], sbox));
+                r[half] = _mm_xor_si128(r[half], ice_f_sse41(l[half], &pair[0], sbox));
+            }
+        }
+    }
+
+    let mut lw = [0u32; 8];
+    let mut rw = [0u32; 8];
+    unsafe {
+        _mm_storeu_si128(lw.as_mut_ptr() as *mut __m128i, l[0]);
+        _mm_storeu_si128(lw.as_mut_ptr().add(4) as *mut __m128i, l[1]);
+        _mm_storeu_si128(rw.as_mut_ptr() as *mut __m128i, r[0]);
+        _mm_storeu_si128(rw.as_mut_ptr().add(4) as *mut __m128i, r[1]);
+    }
+    store_halves(chunk, &lw, &rw);
+}
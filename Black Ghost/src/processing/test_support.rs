@@ -0,0 +1,296 @@
+//! Builds compressed containers for decompressor tests, archives for the entry readers and
+//! Lua chunks for the bytecode tools.
+
+use std::path::PathBuf;
+use crate::BDO_ICE_KEY;
+use crate::models::paz_file::PazFile;
+use crate::processing::raw_ice::RawIce;
+
+pub enum Op {
+    Literal(u8),
+    Match { dist: usize, len: usize },
+}
+
+/// Small deterministic generator so tests do not need an RNG dependency.
+pub struct Lcg(pub u64);
+
+impl Lcg {
+    pub fn next(&mut self) -> u32 {
+        self.0 = self.0.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+        (self.0 >> 33) as u32
+    }
+
+    pub fn below(&mut self, bound: usize) -> usize {
+        self.next() as usize % bound
+    }
+}
+
+struct Encoder {
+    out: Vec<u8>,
+    ctrl_pos: usize,
+    bits: u32,
+    nbits: u32,
+}
+
+impl Encoder {
+    fn push_bit(&mut self, bit: u32) {
+        if self.nbits == 31 {
+            self.flush_word();
+            self.ctrl_pos = self.out.len();
+            self.out.extend_from_slice(&[0; 4]);
+        }
+        self.bits |= bit << self.nbits;
+        self.nbits += 1;
+    }
+
+    fn flush_word(&mut self) {
+        let word = self.bits | (1 << self.nbits);
+        self.out[self.ctrl_pos..self.ctrl_pos + 4].copy_from_slice(&word.to_le_bytes());
+        self.bits = 0;
+        self.nbits = 0;
+    }
+
+    fn match_header(dist: usize, len: usize) -> Vec<u8> {
+        let (dist, len) = (dist as u32, len as u32);
+        if len == 3 && dist < 64 {
+            vec![(dist << 2) as u8]
+        } else if len == 3 && dist < (1 << 14) {
+            ((dist << 2) | 1).to_le_bytes()[..2].to_vec()
+        } else if (3..=18).contains(&len) && dist < (1 << 10) {
+            ((dist << 6) | ((len - 3) << 2) | 2).to_le_bytes()[..2].to_vec()
+        } else if (3..=33).contains(&len) {
+            ((dist << 7) | ((len - 2) << 2) | 3).to_le_bytes()[..3].to_vec()
+        } else {
+            ((dist << 15) | ((len - 3) << 7) | 3).to_le_bytes().to_vec()
+        }
+    }
+}
+
+/// Random ops that always end in enough literals for the decoder's tail handling.
+pub fn random_ops(rng: &mut Lcg, count: usize) -> Vec<Op> {
+    let mut ops = Vec::with_capacity(count + 16);
+    let mut produced = 0usize;
+
+    for _ in 0..count {
+        if produced > 0 && rng.below(3) == 0 {
+            let max_dist = produced.min((1 << 17) - 1);
+            let dist = match rng.below(4) {
+                0 => 1 + rng.below(max_dist.min(8)),
+                1 => 1 + rng.below(max_dist.min(63)),
+                _ => 1 + rng.below(max_dist),
+            };
+            let len = match rng.below(3) {
+                0 => 3,
+                1 => 3 + rng.below(31),
+                _ => 3 + rng.below(256),
+            };
+            ops.push(Op::Match { dist, len });
+            produced += len;
+        } else {
+            // Short alphabets make matches plausible data, not just noise.
+            ops.push(Op::Literal(b'a' + rng.below(8) as u8));
+            produced += 1;
+        }
+    }
+
+    for _ in 0..16 {
+        ops.push(Op::Literal(rng.next() as u8));
+    }
+    ops
+}
+
+/// Encodes `ops` as a compressed container and returns it with the expected output.
+pub fn compress_ops(ops: &[Op], long_header: bool) -> (Vec<u8>, Vec<u8>) {
+    let header_size = if long_header { 9 } else { 3 };
+    let mut enc = Encoder {
+        out: vec![0; header_size + 4],
+        ctrl_pos: header_size,
+        bits: 0,
+        nbits: 0,
+    };
+    let mut expected = Vec::new();
+
+    for op in ops {
+        match *op {
+            Op::Literal(byte) => {
+                enc.push_bit(0);
+                enc.out.push(byte);
+                expected.push(byte);
+            }
+            Op::Match { dist, len } => {
+                enc.push_bit(1);
+                enc.out.extend(Encoder::match_header(dist, len));
+                let start = expected.len() - dist;
+                for i in 0..len {
+                    expected.push(expected[start + i]);
+                }
+            }
+        }
+    }
+    enc.flush_word();
+
+    let mut out = enc.out;
+    if long_header {
+        out[0] = 0x6F;
+        let comp_len = out.len() as u32;
+        out[1..5].copy_from_slice(&comp_len.to_le_bytes());
+        out[5..9].copy_from_slice(&(expected.len() as u32).to_le_bytes());
+    } else {
+        assert!(out.len() < 256 && expected.len() < 256, "short header limited to 255 bytes");
+        out[0] = 0x6D;
+        out[1] = out.len() as u8;
+        out[2] = expected.len() as u8;
+    }
+
+    (out, expected)
+}
+
+/// Little-endian chunk for `local s = "hi\n" print(s, 2)` with one nested empty function.
+pub fn lua_chunk(strip: bool) -> Vec<u8> {
+    fn int(out: &mut Vec<u8>, value: u32) {
+        out.extend_from_slice(&value.to_le_bytes());
+    }
+    fn string(out: &mut Vec<u8>, value: &[u8]) {
+        int(out, value.len() as u32 + 1);
+        out.extend_from_slice(value);
+        out.push(0);
+    }
+    fn abc(op: u32, a: u32, b: u32, c: u32) -> u32 {
+        op | a << 6 | c << 14 | b << 23
+    }
+    fn abx(op: u32, a: u32, bx: u32) -> u32 {
+        op | a << 6 | bx << 14
+    }
+
+    let mut out = b"\x1bLua\x51\x00\x01\x04\x04\x04\x08\x00".to_vec();
+    string(&mut out, b"@test.lua");
+    out.extend_from_slice(&[0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 2, 4]);
+
+    let code = [
+        abx(1, 0, 0),               // LOADK 0 -1
+        abx(5, 1, 1),               // GETGLOBAL 1 -2
+        abc(0, 2, 0, 0),            // MOVE 2 0
+        abx(1, 3, 2),               // LOADK 3 -3
+        abc(28, 1, 3, 1),           // CALL 1 3 1
+        abx(36, 1, 0),              // CLOSURE 1 0
+        abx(22, 0, 131_071),        // JMP 0
+        abc(30, 0, 1, 0),           // RETURN 0 1
+    ];
+    int(&mut out, code.len() as u32);
+    for instruction in code {
+        int(&mut out, instruction);
+    }
+
+    int(&mut out, 3);
+    out.push(4);
+    string(&mut out, b"hi\n");
+    out.push(4);
+    string(&mut out, b"print");
+    out.push(3);
+    out.extend_from_slice(&2.0f64.to_le_bytes());
+
+    int(&mut out, 1);
+    int(&mut out, 0);
+    int(&mut out, 3);
+    int(&mut out, 5);
+    out.extend_from_slice(&[0, 0, 0, 2]);
+    int(&mut out, 1);
+    int(&mut out, abc(30, 0, 1, 0));
+    for _ in 0..5 {
+        int(&mut out, 0);
+    }
+
+    if strip {
+        for _ in 0..3 {
+            int(&mut out, 0);
+        }
+    } else {
+        int(&mut out, code.len() as u32);
+        for line in [1, 1, 1, 1, 1, 5, 6, 6] {
+            int(&mut out, line);
+        }
+        int(&mut out, 1);
+        string(&mut out, b"s");
+        int(&mut out, 1);
+        int(&mut out, 7);
+        int(&mut out, 0);
+    }
+    out
+}
+
+/// Meta entry for `stored` at the start of `pad00001.paz`.
+pub fn paz_file(stored: &[u8], original_size: usize) -> PazFile {
+    PazFile {
+        hash: 0,
+        folder_id: 0,
+        file_id: 0,
+        paz_number: 1,
+        offset: 0,
+        compressed_size: stored.len() as u32,
+        original_size: original_size as u32,
+    }
+}
+
+/// Zero-pads a container to whole ICE blocks, as the game stores encrypted containers.
+pub fn padded(mut stored: Vec<u8>) -> Vec<u8> {
+    stored.resize(stored.len().next_multiple_of(8), 0);
+    stored
+}
+
+/// Encrypts the 8-byte aligned part of `plain` the way the game stores entries.
+pub fn encrypt_entry(plain: &[u8]) -> Vec<u8> {
+    let mut stored = plain.to_vec();
+    let aligned = stored.len() & !7;
+    RawIce::new(0, &BDO_ICE_KEY).encrypt(&mut stored[..aligned]);
+    stored
+}
+
+/// Writes the stored bytes of `entries` back to back into `pad00001.paz` in a new temporary
+/// folder. Returns the folder and a `PazFile` per entry, given its original size.
+pub fn paz_folder(name: &str, entries: &[(Vec<u8>, usize)]) -> (PathBuf, Vec<PazFile>) {
+    let dir = std::env::temp_dir().join(format!("black_ghost_{name}_{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+
+    let mut paz = Vec::new();
+    let mut files = Vec::new();
+    for (i, (stored, original_size)) in entries.iter().enumerate() {
+        files.push(PazFile {
+            hash: i as u32,
+            file_id: i as u32,
+            offset: paz.len() as u32,
+            ..paz_file(stored, *original_size)
+        });
+        paz.extend_from_slice(stored);
+    }
+    std::fs::write(dir.join("pad00001.paz"), paz).unwrap();
+    (dir, files)
+}
+
+/// Compressible content of about `len` bytes as a long-header container, with the expected output.
+pub fn compressed_container(rng: &mut Lcg, len: usize) -> (Vec<u8>, Vec<u8>) {
+    let mut ops = Vec::new();
+    let mut produced = 0;
+    while produced < len {
+        if produced < 64 || rng.below(4) == 0 {
+            ops.push(Op::Literal(rng.next() as u8));
+            produced += 1;
+        } else {
+            let dist = 1 + rng.below(produced.min(4096));
+            let len = 3 + rng.below(250);
+            ops.push(Op::Match { dist, len });
+            produced += len;
+        }
+    }
+    ops.extend((0..16).map(|_| Op::Literal(rng.next() as u8)));
+    compress_ops(&ops, true)
+}
+
+/// Like `compressed_container`, but ending 8 bytes early with the announced compressed length
+/// lowered to match, so the stream ends cleanly short of its original size.
+pub fn short_container(rng: &mut Lcg, len: usize) -> (Vec<u8>, Vec<u8>) {
+    let (mut container, expected) = compressed_container(rng, len);
+    container.truncate(container.len() - 8);
+    let comp_len = container.len() as u32;
+    container[1..5].copy_from_slice(&comp_len.to_le_bytes());
+    (container, expected)
+}
@@ -0,0 +1,268 @@
+use std::fmt::Write as _;
+use std::io::Read;
+use image_dds::ddsfile::{Dds, MiscFlag};
+use image_dds::mip_dimension;
+use rayon::prelude::*;
+use crate::models::bdo_index::BdoIndex;
+use crate::models::paz_archive::PazArchive;
+use crate::models::paz_file::PazFile;
+use crate::processing::dds_format::DdsFormat;
+use crate::processing::dds_repair::{DdsRepair, MAX_HEADER_LEN};
+use crate::util::text_escape::{csv_field, json_string};
+
+/// Texture properties as declared by a DDS header.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TextureInfo {
+    pub width: u32,
+    pub height: u32,
+    pub depth: u32,
+    pub mip_count: u32,
+    /// Array layers, counting each cubemap face as a layer.
+    pub array_size: u32,
+    pub cubemap: bool,
+    /// `DXGI_FORMAT` value, 0 if the format has no DXGI equivalent.
+    pub dxgi_format: u32,
+    pub fourcc: u32,
+    /// DXGI format name, or the FourCC for formats without one.
+    pub format_name: String,
+    /// Size of all layers and mips once decoded to RGBA8.
+    pub decoded_size: u64,
+}
+
+impl TextureInfo {
+    pub fn from_header(dds: &Dds) -> Self {
+        let cubemap = dds.header10.as_ref().is_some_and(|h| h.misc_flag == MiscFlag::TEXTURECUBE)
+            || dds.header.caps2.contains(image_dds::ddsfile::Caps2::CUBEMAP);
+        // DX10 cubemaps count whole cubes; legacy ones already report their six faces.
+        let array_size = match &dds.header10 {
+            Some(h) if h.misc_flag == MiscFlag::TEXTURECUBE => h.array_size.max(1) * 6,
+            _ => dds.get_num_array_layers().max(1),
+        };
+
+        let (width, height, depth) = (dds.get_width(), dds.get_height(), dds.get_depth());
+        let mip_count = dds.get_num_mipmap_levels().max(1);
+        let decoded_size = array_size as u64
+            * (0..mip_count)
+                .map(|mip| {
+                    mip_dimension(width, mip) as u64
+                        * mip_dimension(height, mip) as u64
+                        * mip_dimension(depth, mip) as u64
+                        * 4
+                })
+                .sum::<u64>();
+
+        let dxgi = DdsFormat::dxgi(dds);
+        let fourcc = DdsFormat::fourcc(dds);
+        let format_name = match dxgi {
+            Some(format) => format!("{format:?}"),
+            None if fourcc != 0 => fourcc_name(fourcc),
+            None => "Unknown".to_owned(),
+        };
+
+        Self {
+            width,
+            height,
+            depth,
+            mip_count,
+            array_size,
+            cubemap,
+            dxgi_format: dxgi.map_or(0, |format| format as u32),
+            fourcc,
+            format_name,
+            decoded_size,
+        }
+    }
+}
+
+pub struct TextureCatalogEntry {
+    pub file_index: u32,
+    pub path: String,
+    /// Size of the decoded entry, i.e. of the DDS file itself.
+    pub entry_size: u32,
+    pub info: Result<TextureInfo, String>,
+}
+
+pub struct TextureCatalog {
+    pub entries: Vec<TextureCatalogEntry>,
+}
+
+impl TextureCatalog {
+    /// Reads the header of every `.dds` and `.dds1` entry in parallel. Only the first bytes of
+    /// each entry are decrypted and decompressed.
+    pub fn build(archive: &PazArchive, index: &BdoIndex) -> Self {
+        let mut entries: Vec<TextureCatalogEntry> = index
+            .paz_files
+            .par_iter()
+            .enumerate()
+            .filter_map(|(file_index, file_info)| {
+                let name = index.metadata.file_names[file_info.file_id as usize].to_ascii_lowercase();
+                if !name.ends_with(".dds") && !name.ends_with(".dds1") {
+                    return None;
+                }
+
+                let info = Self::read_header(archive, file_info).and_then(|header| {
+                    DdsRepair::read_header(&header)
+                        .map(|(dds, _)| TextureInfo::from_header(&dds))
+                        .map_err(|e| e.to_string())
+                });
+                Some(TextureCatalogEntry {
+                    file_index: file_index as u32,
+                    path: index.entry_path(file_info),
+                    entry_size: file_info.original_size,
+                    info,
+                })
+            })
+            .collect();
+
+        entries.sort_by(|a, b| a.path.cmp(&b.path));
+        Self { entries }
+    }
+
+    fn read_header(archive: &PazArchive, file_info: &PazFile) -> Result<Vec<u8>, String> {
+        let reader = archive.entry_reader(file_info).ok_or("entry is not readable")?;
+        let mut header = Vec::with_capacity(MAX_HEADER_LEN);
+        reader
+            .take(MAX_HEADER_LEN as u64)
+            .read_to_end(&mut header)
+            .map_err(|e| e.to_string())?;
+        Ok(header)
+    }
+
+    pub fn to_json(&self) -> String {
+        let mut out = String::from("[\n");
+        for (i, entry) in self.entries.iter().enumerate() {
+            let _ = write!(
+                out,
+                "  {{\"file_index\": {}, \"path\": {}, \"entry_size\": {}",
+                entry.file_index,
+                json_string(&entry.path),
+                entry.entry_size
+            );
+            match &entry.info {
+                Ok(info) => {
+                    let _ = write!(
+                        out,
+                        ", \"width\": {}, \"height\": {}, \"depth\": {}, \"mip_count\": {}, \"array_size\": {}, \
+                         \"cubemap\": {}, \"dxgi_format\": {}, \"fourcc\": {}, \"format\": {}, \"decoded_size\": {}}}",
+                        info.width,
+                        info.height,
+                        info.depth,
+                        info.mip_count,
+                        info.array_size,
+                        info.cubemap,
+                        info.dxgi_format,
+                        info.fourcc,
+                        json_string(&info.format_name),
+                        info.decoded_size
+                    );
+                }
+                Err(error) => {
+                    let _ = write!(out, ", \"error\": {}}}", json_string(error));
+                }
+            }
+            out.push_str(if i + 1 < self.entries.len() { ",\n" } else { "\n" });
+        }
+        out.push_str("]\n");
+        out
+    }
+
+    pub fn to_csv(&self) -> String {
+        let mut out = String::from(
+            "file_index,path,entry_size,width,height,depth,mip_count,array_size,cubemap,dxgi_format,fourcc,format,decoded_size,error\n",
+        );
+        for entry in &self.entries {
+            let _ = write!(out, "{},{},{},", entry.file_index, csv_field(&entry.path), entry.entry_size);
+            match &entry.info {
+                Ok(info) => {
+                    let _ = writeln!(
+                        out,
+                        "{},{},{},{},{},{},{},{},{},{},",
+                        info.width,
+                        info.height,
+                        info.depth,
+                        info.mip_count,
+                        info.array_size,
+                        info.cubemap,
+                        info.dxgi_format,
+                        info.fourcc,
+                        csv_field(&info.format_name),
+                        info.decoded_size
+                    );
+                }
+                Err(error) => {
+                    let _ = writeln!(out, ",,,,,,,,,,{}", csv_field(error));
+                }
+            }
+        }
+        out
+    }
+}
+
+fn fourcc_name(fourcc: u32) -> String {
+    let bytes = fourcc.to_le_bytes();
+    if bytes.iter().all(|b| b.is_ascii_graphic() || *b == b' ') {
+        String::from_utf8_lossy(&bytes).trim_end().to_owned()
+    } else {
+        format!("{fourcc:#010x}")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn catalog() -> TextureCatalog {
+        let dds = image_dds::Surface {
+            width: 8,
+            height: 4,
+            depth: 1,
+            layers: 6,
+            mipmaps: 2,
+            image_format: image_dds::ImageFormat::BC1RgbaUnorm,
+            data: vec![0u8; 6 * 2 * 16],
+        }
+        .to_dds()
+        .unwrap();
+
+        TextureCatalog {
+            entries: vec![
+                TextureCatalogEntry {
+                    file_index: 3,
+                    path: "ui_texture/sky,\"night\".dds".to_owned(),
+                    entry_size: 340,
+                    info: Ok(TextureInfo::from_header(&dds)),
+                },
+                TextureCatalogEntry {
+                    file_index: 9,
+                    path: "ui_texture/broken.dds1".to_owned(),
+                    entry_size: 12,
+                    info: Err("no DDS header found in 12 bytes".to_owned()),
+                },
+            ],
+        }
+    }
+
+    #[test]
+    fn reads_header_properties() {
+        let info = catalog().entries[0].info.clone().unwrap();
+        assert_eq!((info.width, info.height, info.mip_count, info.array_size, info.cubemap), (8, 4, 2, 6, true));
+        assert_eq!(info.format_name, "BC1_UNorm");
+        assert_eq!(info.decoded_size, 6 * (8 * 4 + 4 * 2) * 4);
+    }
+
+    #[test]
+    fn exports_escaped_json_and_csv() {
+        let catalog = catalog();
+
+        let json = catalog.to_json();
+        assert!(json.contains(r#""path": "ui_texture/sky,\"night\".dds""#));
+        assert!(json.contains(r#""format": "BC1_UNorm""#));
+        assert!(json.contains(r#""error": "no DDS header found in 12 bytes"}"#));
+
+        let csv = catalog.to_csv();
+        let lines: Vec<_> = csv.lines().collect();
+        assert_eq!(lines.len(), 3);
+        assert!(lines[1].starts_with(r#"3,"ui_texture/sky,""night"".dds",340,8,4,1,2,6,true,71,"#));
+        assert_eq!(lines[2], "9,ui_texture/broken.dds1,12,,,,,,,,,,,no DDS header found in 12 bytes");
+    }
+}
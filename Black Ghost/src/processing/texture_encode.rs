@@ -0,0 +1,107 @@
+use std::io::Cursor;
+use image_dds::image::{self, RgbaImage};
+use image_dds::{CreateDdsError, ImageFormat, Mipmaps, Quality, SurfaceRgba8};
+use crate::models::texture_encode_options::{BlockCompression, EncodeQuality, TextureEncodeOptions};
+use crate::processing::dds_repair::DdsRepair;
+use crate::processing::texture_export::TextureError;
+
+/// Encodes images into DDS files that can replace texture entries.
+pub struct TextureEncode;
+
+impl TextureEncode {
+    /// Loads a PNG or TGA image. TGA has no magic number, so it is recognized by `extension`.
+    pub fn load_image(data: &[u8], extension: &str) -> Result<RgbaImage, TextureError> {
+        let image = if extension.eq_ignore_ascii_case("tga") {
+            image::load_from_memory_with_format(data, image::ImageFormat::Tga)?
+        } else {
+            image::load_from_memory(data)?
+        };
+        Ok(image.to_rgba8())
+    }
+
+    pub fn encode(image: &RgbaImage, options: &TextureEncodeOptions) -> Result<Vec<u8>, TextureError> {
+        let format = match (options.format, options.srgb) {
+            (BlockCompression::Bc1, false) => ImageFormat::BC1RgbaUnorm,
+            (BlockCompression::Bc1, true) => ImageFormat::BC1RgbaUnormSrgb,
+            (BlockCompression::Bc3, false) => ImageFormat::BC3RgbaUnorm,
+            (BlockCompression::Bc3, true) => ImageFormat::BC3RgbaUnormSrgb,
+            (BlockCompression::Bc5, _) => ImageFormat::BC5RgUnorm,
+            (BlockCompression::Bc7, false) => ImageFormat::BC7RgbaUnorm,
+            (BlockCompression::Bc7, true) => ImageFormat::BC7RgbaUnormSrgb,
+        };
+        Self::encode_as(image, format, options.mip_count, options.quality)
+    }
+
+    /// Encodes `image` with the pixel format and mip count of the DDS entry it replaces.
+    /// The mip count is capped to what the new image size allows.
+    pub fn encode_like(image: &RgbaImage, original: &[u8], quality: EncodeQuality) -> Result<Vec<u8>, TextureError> {
+        let (dds, _) = DdsRepair::read(original)?;
+        let format = image_dds::dds_image_format(&dds).map_err(|info| {
+            image_dds::error::SurfaceError::UnsupportedDdsFormat(info)
+        })?;
+        Self::encode_as(image, format, dds.get_num_mipmap_levels().max(1), quality)
+    }
+
+    fn encode_as(image: &RgbaImage, format: ImageFormat, mip_count: u32, quality: EncodeQuality) -> Result<Vec<u8>, TextureError> {
+        let max_mips = u32::BITS - image.width().max(image.height()).leading_zeros();
+        let mipmaps = match mip_count {
+            0 => Mipmaps::GeneratedAutomatic,
+            1 => Mipmaps::Disabled,
+            count => Mipmaps::GeneratedExact(count.min(max_mips)),
+        };
+        let quality = match quality {
+            EncodeQuality::Fast => Quality::Fast,
+            EncodeQuality::Normal => Quality::Normal,
+            EncodeQuality::Slow => Quality::Slow,
+        };
+
+        let dds = SurfaceRgba8::from_image(image).encode_dds(format, quality, mipmaps)?;
+        let mut data = Vec::new();
+        dds.write(&mut Cursor::new(&mut data)).map_err(CreateDdsError::from)?;
+        Ok(data)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image_dds::ddsfile::Dds;
+
+    fn gradient(width: u32, height: u32) -> RgbaImage {
+        RgbaImage::from_fn(width, height, |x, y| image::Rgba([(x * 2) as u8, (y * 2) as u8, 128, 255]))
+    }
+
+    #[test]
+    fn encodes_full_mip_chain_in_requested_format() {
+        let options = TextureEncodeOptions { format: BlockCompression::Bc1, quality: EncodeQuality::Fast, ..Default::default() };
+        let encoded = TextureEncode::encode(&gradient(32, 16), &options).unwrap();
+
+        let (dds, report) = DdsRepair::read(&encoded).unwrap();
+        assert!(!report.is_modified());
+        assert_eq!(image_dds::dds_image_format(&dds).unwrap(), ImageFormat::BC1RgbaUnorm);
+        assert_eq!((dds.get_width(), dds.get_height(), dds.get_num_mipmap_levels()), (32, 16, 6));
+
+        // BC1 keeps a smooth gradient within a few levels of the source.
+        let decoded = image_dds::image_from_dds(&dds, 0).unwrap();
+        let source = gradient(32, 16);
+        for (a, b) in decoded.pixels().zip(source.pixels()) {
+            assert!(a.0.iter().zip(b.0).all(|(&a, b)| a.abs_diff(b) <= 12), "{a:?} vs {b:?}");
+        }
+    }
+
+    #[test]
+    fn matches_format_and_mips_of_original() {
+        let original_options = TextureEncodeOptions {
+            format: BlockCompression::Bc3,
+            srgb: true,
+            mip_count: 3,
+            quality: EncodeQuality::Fast,
+        };
+        let original = TextureEncode::encode(&gradient(16, 16), &original_options).unwrap();
+
+        let encoded = TextureEncode::encode_like(&gradient(32, 32), &original, EncodeQuality::Fast).unwrap();
+        let dds = Dds::read(&mut Cursor::new(encoded)).unwrap();
+        assert_eq!(image_dds::dds_image_format(&dds).unwrap(), ImageFormat::BC3RgbaUnormSrgb);
+        assert_eq!((dds.get_width(), dds.get_num_mipmap_levels()), (32, 3));
+    }
+}
@@ -0,0 +1,342 @@
+use std::fmt;
+use std::io::Cursor;
+use image_dds::ddsfile::{Caps2, Dds, MiscFlag};
+use image_dds::error::SurfaceError;
+use image_dds::image::{imageops, DynamicImage, ImageError, ImageFormat};
+use image_dds::{CreateDdsError, Surface};
+use crate::models::texture_export_options::{AlphaMode, TextureExportOptions, TextureFileFormat, TextureLayout};
+use crate::processing::dds_repair::{DdsRepair, DdsRepairError};
+
+/// Cubemap faces in the order they are stored in a DDS file.
+const CUBE_FACES: [&str; 6] = ["posx", "negx", "posy", "negy", "posz", "negz"];
+
+/// Face position (column, row) in a horizontal cross, four faces wide and three high.
+const CROSS_CELLS: [(u32, u32); 6] = [(2, 1), (0, 1), (1, 0), (1, 2), (1, 1), (3, 1)];
+
+#[derive(Debug)]
+pub enum TextureError {
+    Header(DdsRepairError),
+    Surface(SurfaceError),
+    /// Reading or writing a PNG, TGA, WebP or EXR image failed.
+    Image(ImageError),
+    DdsEncode(CreateDdsError),
+    /// The requested layer or mip level does not exist.
+    OutOfRange {
+        layer: u32,
+        mip: u32,
+        layers: u32,
+        mipmaps: u32,
+    },
+    /// A cross layout was requested for a texture that is not a cubemap.
+    NotCubemap,
+}
+
+impl fmt::Display for TextureError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Header(e) => write!(f, "{e}"),
+            Self::Surface(e) => write!(f, "cannot decode texture: {e}"),
+            Self::Image(e) => write!(f, "image error: {e}"),
+            Self::DdsEncode(e) => write!(f, "cannot encode DDS: {e}"),
+            Self::OutOfRange { layer, mip, layers, mipmaps } => write!(
+                f,
+                "layer {layer}, mip {mip} requested but the texture has {layers} layers and {mipmaps} mips"
+            ),
+            Self::NotCubemap => write!(f, "cross layout requested for a texture that is not a cubemap"),
+        }
+    }
+}
+
+impl std::error::Error for TextureError {}
+
+impl From<DdsRepairError> for TextureError {
+    fn from(e: DdsRepairError) -> Self {
+        Self::Header(e)
+    }
+}
+
+impl From<SurfaceError> for TextureError {
+    fn from(e: SurfaceError) -> Self {
+        Self::Surface(e)
+    }
+}
+
+impl From<CreateDdsError> for TextureError {
+    fn from(e: CreateDdsError) -> Self {
+        Self::DdsEncode(e)
+    }
+}
+
+impl From<ImageError> for TextureError {
+    fn from(e: ImageError) -> Self {
+        Self::Image(e)
+    }
+}
+
+/// One encoded output file. `suffix` distinguishes the files of a multi-image export,
+/// e.g. `_posx_mip2`, and is empty for single images.
+pub struct ExportedImage {
+    pub suffix: String,
+    pub data: Vec<u8>,
+}
+
+pub struct TextureExport;
+
+impl TextureExport {
+    /// Exports `.dds` or `.dds1` data, repairing the header variants `DdsRepair` knows about.
+    pub fn export(dds_buffer: &[u8], options: &TextureExportOptions) -> Result<Vec<ExportedImage>, TextureError> {
+        let (dds, _) = DdsRepair::read(dds_buffer)?;
+        Self::export_dds(&dds, options)
+    }
+
+    pub fn export_dds(dds: &Dds, options: &TextureExportOptions) -> Result<Vec<ExportedImage>, TextureError> {
+        let surface = Surface::from_dds(dds)?;
+        let cubemap = Self::is_cubemap(dds);
+
+        let check_range = |layer: u32, mip: u32| {
+            if layer < surface.layers && mip < surface.mipmaps {
+                Ok(())
+            } else {
+                Err(TextureError::OutOfRange {
+                    layer,
+                    mip,
+                    layers: surface.layers,
+                    mipmaps: surface.mipmaps,
+                })
+            }
+        };
+
+        match options.layout {
+            TextureLayout::Single => {
+                check_range(options.layer, options.mip)?;
+                let slices = Self::decode(&surface, options.layer, options.mip, options)?;
+                let count = slices.len();
+                slices
+                    .into_iter()
+                    .enumerate()
+                    .map(|(slice, image)| {
+                        let suffix = if count > 1 { format!("_slice{slice}") } else { String::new() };
+                        Self::encode(image, suffix, options.format)
+                    })
+                    .collect()
+            }
+            TextureLayout::AllSeparate => {
+                let mut images = Vec::with_capacity((surface.layers * surface.mipmaps) as usize);
+                for layer in 0..surface.layers {
+                    for mip in 0..surface.mipmaps {
+                        let slices = Self::decode(&surface, layer, mip, options)?;
+                        for (slice, image) in slices.into_iter().enumerate() {
+                            let suffix = Self::suffix(&surface, cubemap, layer, slice as u32, mip);
+                            images.push(Self::encode(image, suffix, options.format)?);
+                        }
+                    }
+                }
+                Ok(images)
+            }
+            TextureLayout::CubeCross => {
+                if !cubemap || surface.layers % 6 != 0 {
+                    return Err(TextureError::NotCubemap);
+                }
+                check_range(options.layer, options.mip)?;
+
+                let first_face = options.layer - options.layer % 6;
+                let faces = (first_face..first_face + 6)
+                    .map(|layer| Ok(Self::decode(&surface, layer, options.mip, options)?.swap_remove(0)))
+                    .collect::<Result<Vec<_>, TextureError>>()?;
+
+                let (width, height) = (faces[0].width(), faces[0].height());
+                let mut cross = DynamicImage::new(width * 4, height * 3, faces[0].color());
+                for (face, &(column, row)) in faces.iter().zip(&CROSS_CELLS) {
+                    imageops::replace(&mut cross, face, (column * width) as i64, (row * height) as i64);
+                }
+                Ok(vec![Self::encode(cross, String::new(), options.format)?])
+            }
+        }
+    }
+
+    pub fn is_cubemap(dds: &Dds) -> bool {
+        dds.header.caps2.contains(Caps2::CUBEMAP)
+            || dds.header10.as_ref().is_some_and(|h| h.misc_flag == MiscFlag::TEXTURECUBE)
+    }
+
+    /// Decodes one surface, as 32-bit float for EXR and RGBA8 for everything else.
+    /// Volume textures give one image per depth slice of the mip level, all others exactly one.
+    fn decode(
+        surface: &Surface<&[u8]>,
+        layer: u32,
+        mip: u32,
+        options: &TextureExportOptions,
+    ) -> Result<Vec<DynamicImage>, TextureError> {
+        let out_of_bounds = || SurfaceError::MipmapDataOutOfBounds { layer, mipmap: mip };
+
+        // Slices are decoded as 2D surfaces of their own: image_dds looks for the full base
+        // depth at every mip level, which volume mips past the first do not have.
+        let depth = (surface.depth >> mip).max(1);
+        let mut images = (0..depth)
+            .map(|slice| {
+                let slice_surface = Surface {
+                    width: (surface.width >> mip).max(1),
+                    height: (surface.height >> mip).max(1),
+                    depth: 1,
+                    layers: 1,
+                    mipmaps: 1,
+                    image_format: surface.image_format,
+                    data: surface.get(layer, slice, mip).ok_or_else(out_of_bounds)?,
+                };
+                let image = if options.format == TextureFileFormat::Exr {
+                    slice_surface.decode_rgbaf32()?.get_image(0, 0, 0).map(DynamicImage::ImageRgba32F)
+                } else {
+                    slice_surface.decode_rgba8()?.get_image(0, 0, 0).map(DynamicImage::ImageRgba8)
+                };
+                image.ok_or_else(out_of_bounds)
+            })
+            .collect::<Result<Vec<_>, SurfaceError>>()?;
+
+        if options.alpha == AlphaMode::Premultiplied {
+            images.iter_mut().for_each(Self::premultiply);
+        }
+        Ok(images)
+    }
+
+    fn premultiply(image: &mut DynamicImage) {
+        match image {
+            DynamicImage::ImageRgba8(rgba) => {
+                for pixel in rgba.pixels_mut() {
+                    let alpha = pixel[3] as u32;
+                    for channel in &mut pixel.0[..3] {
+                        *channel = ((*channel as u32 * alpha + 127) / 255) as u8;
+                    }
+                }
+            }
+            DynamicImage::ImageRgba32F(rgba) => {
+                for pixel in rgba.pixels_mut() {
+                    let alpha = pixel[3];
+                    for channel in &mut pixel.0[..3] {
+                        *channel *= alpha;
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn suffix(surface: &Surface<&[u8]>, cubemap: bool, layer: u32, slice: u32, mip: u32) -> String {
+        let mut suffix = String::new();
+        if cubemap {
+            if surface.layers > 6 {
+                suffix.push_str(&format!("_cube{}", layer / 6));
+            }
+            suffix.push('_');
+            suffix.push_str(CUBE_FACES[(layer % 6) as usize]);
+        } else if surface.layers > 1 {
+            suffix.push_str(&format!("_layer{layer}"));
+        }
+        if surface.depth > 1 {
+            suffix.push_str(&format!("_slice{slice}"));
+        }
+        if surface.mipmaps > 1 {
+            suffix.push_str(&format!("_mip{mip}"));
+        }
+        suffix
+    }
+
+    fn encode(image: DynamicImage, suffix: String, format: TextureFileFormat) -> Result<ExportedImage, TextureError> {
+        let image_format = match format {
+            TextureFileFormat::Png => ImageFormat::Png,
+            TextureFileFormat::Tga => ImageFormat::Tga,
+            TextureFileFormat::WebP => ImageFormat::WebP,
+            TextureFileFormat::Exr => ImageFormat::OpenExr,
+        };
+
+        let mut data = Vec::new();
+        image.write_to(&mut Cursor::new(&mut data), image_format)?;
+        Ok(ExportedImage { suffix, data })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Uncompressed 4x4 cubemap whose faces are filled with `face * 40` in every channel.
+    fn cubemap_dds() -> Dds {
+        let data: Vec<u8> = (0..6u8).flat_map(|face| vec![face * 40; 4 * 4 * 4]).collect();
+        Surface {
+            width: 4,
+            height: 4,
+            depth: 1,
+            layers: 6,
+            mipmaps: 1,
+            image_format: image_dds::ImageFormat::Rgba8Unorm,
+            data,
+        }
+        .to_dds()
+        .unwrap()
+    }
+
+    #[test]
+    fn exports_cubemap_faces_separately_and_as_cross() {
+        let dds = cubemap_dds();
+        assert!(TextureExport::is_cubemap(&dds));
+
+        let options = TextureExportOptions { layout: TextureLayout::AllSeparate, ..Default::default() };
+        let images = TextureExport::export_dds(&dds, &options).unwrap();
+        let suffixes: Vec<_> = images.iter().map(|image| image.suffix.as_str()).collect();
+        assert_eq!(suffixes, ["_posx", "_negx", "_posy", "_negy", "_posz", "_negz"]);
+
+        let options = TextureExportOptions { layout: TextureLayout::CubeCross, ..Default::default() };
+        let images = TextureExport::export_dds(&dds, &options).unwrap();
+        let cross = image_dds::image::load_from_memory(&images[0].data).unwrap().to_rgba8();
+        assert_eq!(cross.dimensions(), (16, 12));
+        for (face, &(column, row)) in CROSS_CELLS.iter().enumerate() {
+            assert_eq!(cross.get_pixel(column * 4 + 1, row * 4 + 1)[0], face as u8 * 40, "face {face}");
+        }
+        assert_eq!(cross.get_pixel(0, 0)[3], 0, "empty cells stay transparent");
+    }
+
+    #[test]
+    fn exports_every_depth_slice_of_volume_textures() {
+        // 4x4x4 base level with slices 0, 40, 80, 120 and a 2x2x2 mip with slices 200, 240.
+        let base = (0..4u8).flat_map(|slice| vec![slice * 40; 4 * 4 * 4]);
+        let mip = (0..2u8).flat_map(|slice| vec![200 + slice * 40; 2 * 2 * 4]);
+        let dds = Surface {
+            width: 4,
+            height: 4,
+            depth: 4,
+            layers: 1,
+            mipmaps: 2,
+            image_format: image_dds::ImageFormat::Rgba8Unorm,
+            data: base.chain(mip).collect::<Vec<u8>>(),
+        }
+        .to_dds()
+        .unwrap();
+
+        let options = TextureExportOptions { layout: TextureLayout::AllSeparate, ..Default::default() };
+        let images = TextureExport::export_dds(&dds, &options).unwrap();
+        let suffixes: Vec<_> = images.iter().map(|image| image.suffix.as_str()).collect();
+        assert_eq!(
+            suffixes,
+            ["_slice0_mip0", "_slice1_mip0", "_slice2_mip0", "_slice3_mip0", "_slice0_mip1", "_slice1_mip1"]
+        );
+        let values: Vec<u8> = images
+            .iter()
+            .map(|image| image_dds::image::load_from_memory(&image.data).unwrap().to_rgba8()[(0, 0)][0])
+            .collect();
+        assert_eq!(values, [0, 40, 80, 120, 200, 240]);
+
+        let options = TextureExportOptions { mip: 1, ..Default::default() };
+        let images = TextureExport::export_dds(&dds, &options).unwrap();
+        assert_eq!(images.len(), 2);
+        assert_eq!(images[1].suffix, "_slice1");
+    }
+
+    #[test]
+    fn rejects_missing_surfaces() {
+        let dds = cubemap_dds();
+        let options = TextureExportOptions { layer: 6, ..Default::default() };
+        assert!(matches!(
+            TextureExport::export_dds(&dds, &options),
+            Err(TextureError::OutOfRange { layers: 6, .. })
+        ));
+    }
+}
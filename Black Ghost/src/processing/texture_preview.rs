@@ -0,0 +1,87 @@
+use image_dds::ddsfile::Dds;
+use image_dds::image::{imageops, RgbaImage};
+use image_dds::{mip_dimension, Surface};
+use crate::processing::texture_export::TextureError;
+
+pub struct TexturePreview;
+
+impl TexturePreview {
+    /// Decodes the first layer of `dds` to RGBA8, starting at `mip` and stepping down to
+    /// smaller mips until neither side exceeds `max_dim` (0 means no limit). If even the
+    /// smallest mip is too large it is downscaled. Returns the image and the mip it came from.
+    pub fn decode(dds: &Dds, mip: u32, max_dim: u32) -> Result<(RgbaImage, u32), TextureError> {
+        let surface = Surface::from_dds(dds)?;
+        if mip >= surface.mipmaps {
+            return Err(TextureError::OutOfRange {
+                layer: 0,
+                mip,
+                layers: surface.layers,
+                mipmaps: surface.mipmaps,
+            });
+        }
+
+        let fits = |mip: u32| {
+            max_dim == 0
+                || mip_dimension(surface.width, mip).max(mip_dimension(surface.height, mip)) <= max_dim
+        };
+        let mut chosen = mip;
+        while !fits(chosen) && chosen + 1 < surface.mipmaps {
+            chosen += 1;
+        }
+
+        let decoded = surface.decode_layers_mipmaps_rgba8(0..1, chosen..chosen + 1)?;
+        let image = decoded
+            .get_image(0, 0, 0)
+            .ok_or(image_dds::error::SurfaceError::MipmapDataOutOfBounds { layer: 0, mipmap: chosen })?;
+
+        if fits(chosen) {
+            return Ok((image, chosen));
+        }
+
+        let scale = max_dim as f64 / image.width().max(image.height()) as f64;
+        let width = ((image.width() as f64 * scale).round() as u32).max(1);
+        let height = ((image.height() as f64 * scale).round() as u32).max(1);
+        Ok((imageops::resize(&image, width, height, imageops::FilterType::Triangle), chosen))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rgba_dds(width: u32, height: u32, mipmaps: u32) -> Dds {
+        let len: u32 = (0..mipmaps).map(|mip| mip_dimension(width, mip) * mip_dimension(height, mip) * 4).sum();
+        Surface {
+            width,
+            height,
+            depth: 1,
+            layers: 1,
+            mipmaps,
+            image_format: image_dds::ImageFormat::Rgba8Unorm,
+            data: vec![0x80u8; len as usize],
+        }
+        .to_dds()
+        .unwrap()
+    }
+
+    #[test]
+    fn picks_first_mip_that_fits() {
+        let dds = rgba_dds(64, 32, 7);
+
+        let (image, mip) = TexturePreview::decode(&dds, 0, 0).unwrap();
+        assert_eq!((image.dimensions(), mip), ((64, 32), 0));
+
+        let (image, mip) = TexturePreview::decode(&dds, 0, 16).unwrap();
+        assert_eq!((image.dimensions(), mip), ((16, 8), 2));
+
+        let (image, mip) = TexturePreview::decode(&dds, 3, 100).unwrap();
+        assert_eq!((image.dimensions(), mip), ((8, 4), 3));
+    }
+
+    #[test]
+    fn downscales_when_no_mip_fits() {
+        let dds = rgba_dds(64, 32, 1);
+        let (image, mip) = TexturePreview::decode(&dds, 0, 10).unwrap();
+        assert_eq!((image.dimensions(), mip), ((10, 5), 0));
+    }
+}
@@ -0,0 +1,185 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use image_dds::image::RgbaImage;
+use rayon::prelude::*;
+use crate::models::bdo_index::BdoIndex;
+use crate::models::paz_archive::PazArchive;
+use crate::models::paz_file::PazFile;
+use crate::processing::dds_repair::DdsRepair;
+use crate::processing::paz_entry::PazEntry;
+use crate::processing::texture_preview::TexturePreview;
+
+const CACHE_MAGIC: &[u8; 4] = b"BGT1";
+
+static TEMP_COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+/// Decodes small previews of texture entries, backed by an optional on-disk cache.
+///
+/// Cache files are named after the entry hash, its stored and original sizes and the
+/// thumbnail size, so a patched entry or a different size never hits a stale file.
+pub struct ThumbnailGenerator<'a> {
+    archive: &'a PazArchive,
+    cache_dir: Option<PathBuf>,
+    max_dim: u32,
+}
+
+impl<'a> ThumbnailGenerator<'a> {
+    pub fn new(archive: &'a PazArchive, cache_dir: Option<&Path>, max_dim: u32) -> Self {
+        if let Some(dir) = cache_dir {
+            let _ = fs::create_dir_all(dir);
+        }
+        Self {
+            archive,
+            cache_dir: cache_dir.map(Path::to_path_buf),
+            max_dim,
+        }
+    }
+
+    /// Thumbnails for `file_indices` in the same order; `None` where an entry is missing or
+    /// cannot be decoded.
+    pub fn generate_all(&self, index: &BdoIndex, file_indices: &[u32]) -> Vec<Option<RgbaImage>> {
+        file_indices
+            .par_iter()
+            .map(|&file_index| {
+                let file_info = index.paz_files.get(file_index as usize)?;
+                self.generate(file_info)
+            })
+            .collect()
+    }
+
+    pub fn generate(&self, file_info: &PazFile) -> Option<RgbaImage> {
+        let cache_path = self.cache_path(file_info);
+        if let Some(image) = cache_path.as_deref().and_then(Self::load_cached) {
+            return Some(image);
+        }
+
+        // Decoded straight from the mapping: a thumbnail is made once per entry and must not
+        // push the entries being browsed out of the archive's caches.
+        let stored = self.archive.stored_bytes(file_info)?;
+        let data = PazEntry::decode(stored, file_info).ok()?;
+        let (dds, _) = DdsRepair::read(&data).ok()?;
+        let (image, _) = TexturePreview::decode(&dds, 0, self.max_dim).ok()?;
+
+        if let Some(path) = cache_path {
+            Self::store_cached(&path, &image);
+        }
+        Some(image)
+    }
+
+    fn cache_path(&self, file_info: &PazFile) -> Option<PathBuf> {
+        let dir = self.cache_dir.as_ref()?;
+        Some(dir.join(format!(
+            "{:08x}_{:08x}_{:08x}_{}.thumb",
+            file_info.hash, file_info.compressed_size, file_info.original_size, self.max_dim
+        )))
+    }
+
+    fn load_cached(path: &Path) -> Option<RgbaImage> {
+        let data = fs::read(path).ok()?;
+        if data.len() < 12 || &data[..4] != CACHE_MAGIC {
+            return None;
+        }
+        let width = u32::from_le_bytes(data[4..8].try_into().unwrap());
+        let height = u32::from_le_bytes(data[8..12].try_into().unwrap());
+        RgbaImage::from_raw(width, height, data[12..].to_vec())
+    }
+
+    fn store_cached(path: &Path, image: &RgbaImage) {
+        let mut data = Vec::with_capacity(12 + image.as_raw().len());
+        data.extend_from_slice(CACHE_MAGIC);
+        data.extend_from_slice(&image.width().to_le_bytes());
+        data.extend_from_slice(&image.height().to_le_bytes());
+        data.extend_from_slice(image.as_raw());
+
+        // Written under a temporary name so concurrent readers never see a partial file.
+        let unique = TEMP_COUNTER.fetch_add(1, Ordering::Relaxed);
+        let temp = path.with_extension(format!("tmp{}_{}", std::process::id(), unique));
+        if fs::write(&temp, &data).is_ok() && fs::rename(&temp, path).is_err() {
+            let _ = fs::remove_file(&temp);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image_dds::{ImageFormat, Surface};
+    use crate::processing::test_support::paz_folder;
+
+    /// Square RGBA texture with a full mip chain; every pixel of mip `n` is `[shade + n, 0, 0, 255]`.
+    fn texture(size: u32, shade: u8) -> Vec<u8> {
+        let mipmaps = size.ilog2() + 1;
+        let data: Vec<u8> = (0..mipmaps)
+            .flat_map(|mip| {
+                let dim = (size >> mip) as usize;
+                [shade + mip as u8, 0, 0, 255].repeat(dim * dim)
+            })
+            .collect();
+        let dds = Surface {
+            width: size,
+            height: size,
+            depth: 1,
+            layers: 1,
+            mipmaps,
+            image_format: ImageFormat::Rgba8Unorm,
+            data,
+        }
+        .to_dds()
+        .unwrap();
+
+        let mut file = Vec::new();
+        dds.write(&mut file).unwrap();
+        file
+    }
+
+    #[test]
+    fn picks_the_smallest_mip_that_fits() {
+        let file = texture(16, 0x40);
+        let (dir, files) = paz_folder("thumbs_mips", &[(file.clone(), file.len())]);
+        let archive = PazArchive::open(dir.to_str().unwrap());
+
+        for (max_dim, mip) in [(16, 0), (15, 1), (8, 1), (5, 2), (1, 4), (0, 0)] {
+            let image = ThumbnailGenerator::new(&archive, None, max_dim).generate(&files[0]).unwrap();
+            assert_eq!(image.dimensions(), (16 >> mip, 16 >> mip), "max_dim {max_dim}");
+            assert_eq!(image.get_pixel(0, 0).0, [0x40 + mip as u8, 0, 0, 255], "max_dim {max_dim}");
+        }
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn patched_entries_miss_cached_thumbnails() {
+        let old = texture(16, 0x10);
+        let new = texture(8, 0x80);
+        let (dir, mut files) = paz_folder("thumbs_patched", &[(old.clone(), old.len()), (new.clone(), new.len())]);
+        files[1].hash = files[0].hash;
+        let archive = PazArchive::open(dir.to_str().unwrap());
+        let cache = dir.join("thumbs");
+        let generator = ThumbnailGenerator::new(&archive, Some(&cache), 16);
+
+        let before = generator.generate(&files[0]).unwrap();
+        assert_eq!(before.get_pixel(0, 0).0, [0x10, 0, 0, 255]);
+
+        // Same hash with new sizes, as after a patch: the old thumbnail must not come back.
+        let after = generator.generate(&files[1]).unwrap();
+        assert_eq!(after.dimensions(), (8, 8));
+        assert_eq!(after.get_pixel(0, 0).0, [0x80, 0, 0, 255]);
+        assert_eq!(fs::read_dir(&cache).unwrap().count(), 2);
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn cache_files_round_trip() {
+        let dir = std::env::temp_dir().join(format!("black_ghost_thumbs_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("entry.thumb");
+
+        let image = RgbaImage::from_fn(5, 3, |x, y| image_dds::image::Rgba([x as u8, y as u8, 7, 255]));
+        ThumbnailGenerator::store_cached(&path, &image);
+        assert_eq!(ThumbnailGenerator::load_cached(&path), Some(image));
+
+        fs::write(&path, b"not a thumbnail").unwrap();
+        assert_eq!(ThumbnailGenerator::load_cached(&path), None);
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,95 @@
+use std::collections::VecDeque;
+use std::sync::Arc;
+
+/// Least recently used cache of decoded buffers, bounded by their total size in bytes.
+pub struct BufferCache<K: PartialEq> {
+    capacity_bytes: usize,
+    used_bytes: usize,
+    // Most recently used entries live at the back.
+    entries: VecDeque<(K, Arc<Vec<u8>>)>,
+}
+
+impl<K: PartialEq> BufferCache<K> {
+    pub fn new(capacity_bytes: usize) -> Self {
+        Self {
+            capacity_bytes,
+            used_bytes: 0,
+            entries: VecDeque::new(),
+        }
+    }
+
+    pub fn get(&mut self, key: &K) -> Option<Arc<Vec<u8>>> {
+        let pos = self.entries.iter().position(|(k, _)| k == key)?;
+        let entry = self.entries.remove(pos)?;
+        let buffer = entry.1.clone();
+        self.entries.push_back(entry);
+        Some(buffer)
+    }
+
+    pub fn insert(&mut self, key: K, buffer: Arc<Vec<u8>>) {
+        if let Some(pos) = self.entries.iter().position(|(k, _)| *k == key)
+            && let Some((_, old)) = self.entries.remove(pos)
+        {
+            self.used_bytes -= old.len();
+        }
+
+        // Buffers larger than the whole cache are handed out without being kept.
+        if buffer.len() > self.capacity_bytes {
+            return;
+        }
+
+        while self.used_bytes + buffer.len() > self.capacity_bytes {
+            match self.entries.pop_front() {
+                Some((_, evicted)) => self.used_bytes -= evicted.len(),
+                None => break,
+            }
+        }
+
+        self.used_bytes += buffer.len();
+        self.entries.push_back((key, buffer));
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+        self.used_bytes = 0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn buffer(len: usize) -> Arc<Vec<u8>> {
+        Arc::new(vec![0; len])
+    }
+
+    #[test]
+    fn evicts_least_recently_used_buffers() {
+        let mut cache = BufferCache::new(100);
+        cache.insert(1, buffer(40));
+        cache.insert(2, buffer(40));
+        assert!(cache.get(&1).is_some());
+
+        cache.insert(3, buffer(40));
+        assert!(cache.get(&2).is_none(), "2 was used least recently");
+        assert!(cache.get(&1).is_some() && cache.get(&3).is_some());
+
+        // Replacing a key frees the old buffer's bytes first.
+        cache.insert(3, buffer(60));
+        assert_eq!(cache.used_bytes, 100);
+        assert!(cache.get(&1).is_some());
+    }
+
+    #[test]
+    fn does_not_keep_buffers_larger_than_the_cache() {
+        let mut cache = BufferCache::new(100);
+        cache.insert(1, buffer(50));
+        cache.insert(2, buffer(101));
+        assert!(cache.get(&2).is_none());
+        assert!(cache.get(&1).is_some(), "an oversized buffer must not evict the others");
+
+        cache.insert(1, buffer(101));
+        assert!(cache.get(&1).is_none());
+        assert_eq!(cache.used_bytes, 0);
+    }
+}
@@ -1 +1,3 @@
-﻿pub mod folder_name_tuple;
\ No newline at end of file
+﻿pub mod folder_name_tuple;
+pub mod buffer_cache;
+pub mod text_escape;
\ No newline at end of file